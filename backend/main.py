@@ -11,10 +11,22 @@ All simulation logic lives in the ``core/`` package; this module
 orchestrates the pipeline and serves the HTTP API.
 """
 
-from fastapi import FastAPI, HTTPException
+import asyncio
+import json
+import logging
+import mimetypes
+import os
+import shutil
+import time
+import uuid
+from pathlib import Path
+
+from fastapi import BackgroundTasks, FastAPI, HTTPException, Request
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import FileResponse, JSONResponse
 
 from schemas import (
+    RunRequest,
     SimulationRequest,
     SimulationResponse,
     SweepRequest,
@@ -61,6 +73,39 @@ app.add_middleware(
     allow_headers=["*"],
 )
 
+# Set by the desktop app for the backend it spawns, so it can tell this process apart from a
+# leftover backend still holding the port from an earlier run
+INSTANCE_ID = os.environ.get("QKD_INSTANCE_ID")
+
+
+@app.middleware("http")
+async def identify_instance(request: Request, call_next):
+    response = await call_next(request)
+    if INSTANCE_ID:
+        response.headers["X-QKD-Backend"] = INSTANCE_ID
+    return response
+
+
+# Routes that run a key exchange, tracked so a drain can wait for them to finish
+SESSION_ROUTES = ("/simulate", "/sweep", "/monte-carlo", "/runs")
+_active_sessions = 0
+_draining = False
+
+
+@app.middleware("http")
+async def track_sessions(request: Request, call_next):
+    global _active_sessions
+    if request.method != "POST" or not request.url.path.startswith(SESSION_ROUTES):
+        return await call_next(request)
+    if _draining:
+        return JSONResponse(status_code=503, content={"detail": "Backend is draining for shutdown"})
+    _active_sessions += 1
+    try:
+        return await call_next(request)
+    finally:
+        _active_sessions -= 1
+
+
 # ---------------------------------------------------------------------------
 # Simulation pipeline
 # ---------------------------------------------------------------------------
@@ -374,6 +419,332 @@ async def sweep_param(request: GenericSweepRequest) -> GenericSweepResponse:
 
 
 @app.get("/health")
-async def health() -> dict[str, str]:
-    """Simple liveness probe."""
-    return {"status": "ok"}
+async def health() -> dict[str, str | bool]:
+    """Simple liveness probe, also reporting whether the QKD engine is loaded."""
+    return {"status": "ok", "qkd_engine": True}
+
+
+@app.get("/version")
+async def version() -> dict[str, str | list[str] | None]:
+    """Build information for support and bug reports."""
+    return {
+        "version": app.version,
+        "git_sha": os.environ.get("QKD_GIT_SHA"),
+        "qkd_protocols": ["bb84"],
+    }
+
+
+@app.get("/protocols")
+async def protocols() -> list[dict]:
+    """Supported QKD protocols and the parameters each accepts."""
+    parameters = [
+        {
+            "name": name,
+            "type": getattr(field.annotation, "__name__", str(field.annotation)),
+            "description": field.description,
+            "default": None if field.is_required() else field.default,
+            "required": field.is_required(),
+        }
+        for name, field in SimulationRequest.model_fields.items()
+    ]
+    return [
+        {
+            "name": "bb84",
+            "display_name": "BB84",
+            "description": "Bennett-Brassard 1984 prepare-and-measure protocol with intercept-resend eavesdropping.",
+            "parameters": parameters,
+        }
+    ]
+
+
+# ---------------------------------------------------------------------------
+# Runtime log level
+# ---------------------------------------------------------------------------
+
+LOG_LEVELS = ("DEBUG", "INFO", "WARNING", "ERROR")
+
+
+@app.post("/loglevel")
+async def set_log_level(body: dict[str, str]) -> dict[str, str]:
+    """Change the server's log level without restarting it."""
+    level = body.get("level", "").upper()
+    if level not in LOG_LEVELS:
+        raise HTTPException(status_code=422, detail=f"level must be one of {', '.join(LOG_LEVELS)}")
+    for name in ("", "uvicorn", "uvicorn.error", "uvicorn.access"):
+        logging.getLogger(name).setLevel(level)
+    return {"level": level}
+
+
+# ---------------------------------------------------------------------------
+# Config reload
+# ---------------------------------------------------------------------------
+
+
+@app.post("/reload")
+async def reload_config(body: dict[str, dict[str, str]]) -> dict[str, list[str]]:
+    """Apply new QKD_* environment settings in place, keeping in-memory state."""
+    env = {key: value for key, value in body.get("env", {}).items() if key.startswith("QKD_")}
+    os.environ.update(env)
+    level = env.get("QKD_LOG_LEVEL", "").upper()
+    if level in LOG_LEVELS:
+        for name in ("", "uvicorn", "uvicorn.error", "uvicorn.access"):
+            logging.getLogger(name).setLevel(level)
+    return {"reloaded": sorted(env)}
+
+
+# ---------------------------------------------------------------------------
+# Drain
+# ---------------------------------------------------------------------------
+
+
+@app.post("/drain")
+async def start_drain() -> dict[str, int | bool]:
+    """Refuse new sessions so the running ones can finish before the app stops the backend."""
+    global _draining
+    _draining = True
+    return {"draining": True, "active": _active_sessions}
+
+
+@app.get("/drain")
+async def drain_status() -> dict[str, int | bool]:
+    """Whether a drain was requested and how many sessions are still running."""
+    return {"draining": _draining, "active": _active_sessions}
+
+
+# ---------------------------------------------------------------------------
+# Preflight
+# ---------------------------------------------------------------------------
+
+# Below this much free disk space exports start failing, below the second the backend can't work
+LOW_DISK_BYTES = 100 * 1024 * 1024
+CRITICAL_DISK_BYTES = 10 * 1024 * 1024
+
+
+def _preflight_check(name: str, status: str, message: str, hint: str | None = None) -> dict[str, str | None]:
+    return {"name": name, "status": status, "message": message, "hint": hint}
+
+
+@app.get("/preflight")
+async def preflight() -> list[dict[str, str | None]]:
+    """Runtime prerequisites, each ``ok``, ``warn`` or ``fail`` with a hint on fixing it."""
+    checks = []
+
+    numpy_version = _np.__version__
+    if numpy_version.startswith("1."):
+        checks.append(_preflight_check("numpy", "ok", f"numpy {numpy_version}"))
+    else:
+        checks.append(_preflight_check(
+            "numpy", "warn", f"numpy {numpy_version} is untested",
+            "Install the pinned versions with pip install -r requirements.txt",
+        ))
+
+    cwd = os.getcwd()
+    if os.access(cwd, os.W_OK):
+        checks.append(_preflight_check("working_dir", "ok", cwd))
+    else:
+        checks.append(_preflight_check(
+            "working_dir", "fail", f"{cwd} is not writable",
+            "Choose a writable working directory in the backend settings",
+        ))
+
+    free = shutil.disk_usage(cwd).free
+    message = f"{free // (1024 * 1024)} MB free"
+    if free < CRITICAL_DISK_BYTES:
+        checks.append(_preflight_check("disk_space", "fail", message, "Free up disk space and restart the backend"))
+    elif free < LOW_DISK_BYTES:
+        checks.append(_preflight_check("disk_space", "warn", message, "Large sweeps and exports may fail, free up disk space"))
+    else:
+        checks.append(_preflight_check("disk_space", "ok", message))
+
+    return checks
+
+
+# ---------------------------------------------------------------------------
+# Self-test
+# ---------------------------------------------------------------------------
+
+
+def _selftest_rng_balance() -> tuple[bool, str]:
+    """The generator should produce ones and zeros in close to equal measure."""
+    ones = float(create_rng().integers(0, 2, size=100_000).mean())
+    return 0.49 <= ones <= 0.51, f"fraction of ones {ones:.4f}"
+
+
+def _selftest_clean_channel() -> tuple[bool, str]:
+    """Without noise or Eve, Alice and Bob must agree on every sifted bit."""
+    result = run_simulation(SimulationRequest(photons=20_000, distance=0.0, seed=1))
+    passed = result.mismatches == 0 and result.security_status == "SECURE"
+    return passed, f"QBER {result.qber:.4f}, {result.security_status}"
+
+
+def _selftest_intercept_resend() -> tuple[bool, str]:
+    """A full intercept-resend attack should push QBER to about 25% and be detected."""
+    result = run_simulation(
+        SimulationRequest(photons=20_000, distance=0.0, eve_enabled=True, eve_probability=1.0, seed=1)
+    )
+    passed = 0.2 <= result.qber <= 0.3 and result.security_status == "COMPROMISED"
+    return passed, f"QBER {result.qber:.4f}, {result.security_status}"
+
+
+SELFTESTS = {
+    "rng_balance": _selftest_rng_balance,
+    "bb84_clean_channel": _selftest_clean_channel,
+    "bb84_intercept_resend": _selftest_intercept_resend,
+}
+
+
+@app.get("/selftest")
+async def list_selftests() -> list[str]:
+    """Names of the available self-test checks, run one at a time with POST /selftest/{name}."""
+    return list(SELFTESTS)
+
+
+@app.post("/selftest/{name}")
+async def run_selftest(name: str) -> dict[str, str | bool]:
+    """Run one self-test check and report whether it passed."""
+    check = SELFTESTS.get(name)
+    if check is None:
+        raise HTTPException(status_code=404, detail=f"Unknown self-test '{name}'")
+    try:
+        passed, detail = check()
+    except Exception as exc:
+        return {"name": name, "passed": False, "detail": f"raised {exc!r}"}
+    return {"name": name, "passed": passed, "detail": detail}
+
+
+# ---------------------------------------------------------------------------
+# Background protocol runs
+# ---------------------------------------------------------------------------
+
+RUN_PROTOCOLS = ("bb84",)
+# Runs by session id: state is "active", "completed" or "failed"
+_runs: dict[str, dict] = {}
+# Monotonic start and end time of each run, the end is None while it runs
+_run_times: dict[str, tuple[float, float | None]] = {}
+# Finished runs beyond this many are forgotten oldest first, their files stay under RESULTS_DIR
+MAX_RUNS = 256
+# Output files of the runs, one directory per session id, served under /results
+RESULTS_DIR = Path(os.environ.get("QKD_RESULTS_DIR", "results")).resolve()
+
+
+def _write_results(session_id: str, result: SimulationResponse) -> None:
+    """Save a completed run's result and bit samples under RESULTS_DIR."""
+    run_dir = RESULTS_DIR / session_id
+    run_dir.mkdir(parents=True, exist_ok=True)
+    (run_dir / "result.json").write_text(json.dumps(result.model_dump(), indent=2))
+    rows = ["index,alice,bob"]
+    for index in range(max(len(result.raw_bits_sample), len(result.bob_bits_sample))):
+        alice = result.raw_bits_sample[index] if index < len(result.raw_bits_sample) else ""
+        bob = result.bob_bits_sample[index] if index < len(result.bob_bits_sample) else ""
+        rows.append(f"{index},{alice},{bob}")
+    (run_dir / "bits.csv").write_text("\n".join(rows) + "\n")
+
+
+async def _execute_run(session_id: str, params: SimulationRequest) -> None:
+    global _active_sessions
+    _active_sessions += 1
+    try:
+        result = await asyncio.to_thread(run_simulation, params)
+        await asyncio.to_thread(_write_results, session_id, result)
+        _runs[session_id] = {"state": "completed", "result": result.model_dump()}
+    except Exception as exc:
+        logging.getLogger(__name__).exception("Run %s failed", session_id)
+        _runs[session_id] = {"state": "failed", "error": str(exc)}
+    finally:
+        _active_sessions -= 1
+        _run_times[session_id] = (_run_times[session_id][0], time.monotonic())
+
+
+def _forget_old_runs() -> None:
+    """Drop the oldest finished runs so that a new one fits within MAX_RUNS. Active runs are kept."""
+    excess = len(_runs) + 1 - MAX_RUNS
+    # Dicts keep insertion order, so the oldest runs come first
+    for session_id in [sid for sid, run in _runs.items() if run["state"] != "active"][:max(excess, 0)]:
+        del _runs[session_id]
+        del _run_times[session_id]
+
+
+@app.post("/runs")
+async def start_run(request: RunRequest, background: BackgroundTasks) -> dict[str, str]:
+    """Start a simulation in the background and return the session id to follow it by."""
+    protocol = request.protocol.lower()
+    if protocol not in RUN_PROTOCOLS:
+        raise HTTPException(
+            status_code=422,
+            detail=[{"loc": ["body", "protocol"], "msg": f"Unknown protocol '{request.protocol}'", "type": "value_error"}],
+        )
+    session_id = uuid.uuid4().hex
+    _forget_old_runs()
+    _runs[session_id] = {"state": "active"}
+    _run_times[session_id] = (time.monotonic(), None)
+    background.add_task(_execute_run, session_id, SimulationRequest(**request.model_dump(exclude={"protocol"})))
+    return {"session_id": session_id}
+
+
+@app.get("/runs/{session_id}")
+async def run_status(session_id: str) -> dict:
+    """State of a run started with POST /runs, with its result once completed."""
+    run = _runs.get(session_id)
+    if run is None:
+        raise HTTPException(status_code=404, detail=f"Unknown run '{session_id}'")
+    return {"session_id": session_id, **run}
+
+
+@app.get("/runs/{session_id}/artifacts")
+async def run_artifacts(session_id: str) -> dict[str, list[dict[str, str | int]]]:
+    """
+    Output files of a run, empty until it completes. Fetch them from their path. A run this
+    process doesn't know, e.g. one from before a restart, lists whatever is still on disk for it.
+    """
+    run_dir = (RESULTS_DIR / session_id).resolve()
+    # Resolving first means ".." can't list anything outside RESULTS_DIR
+    files = sorted(run_dir.iterdir()) if run_dir.parent == RESULTS_DIR and run_dir.is_dir() else []
+    return {
+        "artifacts": [
+            {
+                "name": file.name,
+                "size": file.stat().st_size,
+                "type": mimetypes.guess_type(file.name)[0] or "application/octet-stream",
+                "path": f"/results/{session_id}/{file.name}",
+            }
+            for file in files
+            if file.is_file()
+        ]
+    }
+
+
+@app.get("/results/{session_id}/{name}")
+async def result_file(session_id: str, name: str) -> FileResponse:
+    """Download an output file of a run."""
+    path = (RESULTS_DIR / session_id / name).resolve()
+    # Resolving first means ".." in either part can't reach outside the run's directory
+    if path.parent.parent != RESULTS_DIR or not path.is_file():
+        raise HTTPException(status_code=404, detail=f"No result file '{name}' for run '{session_id}'")
+    return FileResponse(path)
+
+
+@app.get("/sessions/{session_id}/metrics")
+async def session_metrics(session_id: str) -> dict:
+    """
+    QBER and key rates of a run started with POST /runs. A run is a single simulation, so the
+    figures are zero until it completes; the rates are bits per second of run time.
+    """
+    run = _runs.get(session_id)
+    if run is None:
+        raise HTTPException(status_code=404, detail=f"Unknown run '{session_id}'")
+    started, finished = _run_times[session_id]
+    elapsed = (finished if finished is not None else time.monotonic()) - started
+    metrics = {
+        "qber": 0.0,
+        "sifted_key_rate": 0.0,
+        "raw_key_rate": 0.0,
+        "elapsed": elapsed,
+        "state": run["state"],
+    }
+    result = run.get("result")
+    if result is not None:
+        metrics["qber"] = result["qber"]
+        if elapsed > 0:
+            metrics["sifted_key_rate"] = result["sifted_key_length"] / elapsed
+            metrics["raw_key_rate"] = result["total_photons"] / elapsed
+    return metrics
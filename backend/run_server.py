@@ -7,12 +7,13 @@ Cross-platform script to start the FastAPI backend server.
 Can be run standalone or bundled with the Tauri application.
 
 Usage:
-    python run_server.py [--host HOST] [--port PORT] [--reload]
+    python run_server.py [--host HOST] [--port PORT] [--reload] [--seed SEED]
 
 Environment Variables:
     QKD_HOST    - Server host (default: 127.0.0.1)
     QKD_PORT    - Server port (default: 8000)
     QKD_RELOAD  - Enable auto-reload (default: false)
+    QKD_SEED    - Session RNG seed for reproducible simulations (default: random)
 """
 
 import argparse
@@ -47,7 +48,16 @@ def main():
         default=os.environ.get("QKD_RELOAD", "").lower() in ("true", "1", "yes"),
         help="Enable auto-reload for development",
     )
+    parser.add_argument(
+        "--seed",
+        type=int,
+        default=None,
+        help="Session RNG seed for reproducible simulations (default: QKD_SEED or random)",
+    )
     args = parser.parse_args()
+    if args.seed is not None:
+        # Read by utils.helpers when the app is imported
+        os.environ["QKD_SEED"] = str(args.seed)
 
     try:
         import uvicorn
@@ -60,6 +70,7 @@ def main():
     print(f"   Host: {args.host}")
     print(f"   Port: {args.port}")
     print(f"   Reload: {args.reload}")
+    print(f"   Seed: {os.environ.get('QKD_SEED', 'random')}")
     print(f"   API Docs: http://{args.host}:{args.port}/docs")
     print()
 
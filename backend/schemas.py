@@ -87,6 +87,15 @@ class SimulationRequest(BaseModel):
     }
 
 
+class RunRequest(SimulationRequest):
+    """A simulation started in the background with POST /runs."""
+
+    protocol: str = Field(
+        "bb84",
+        description="Protocol to run, one of those listed by GET /protocols.",
+    )
+
+
 class SweepRequest(BaseModel):
     """Parameters for a parameter-sweep across distance or noise."""
 
@@ -11,6 +11,9 @@ import os
 import sys
 import signal
 
+# Single line the desktop shell watches for to know the server is accepting connections
+READY_MARKER = "QKD_BACKEND_READY"
+
 # When running as a PyInstaller bundle, we need to set up paths correctly
 if getattr(sys, 'frozen', False):
     # Running as compiled executable
@@ -27,6 +30,8 @@ def main():
     # Get configuration from environment or use defaults
     host = os.environ.get("QKD_HOST", "127.0.0.1")
     port = int(os.environ.get("QKD_PORT", "8000"))
+    # Listen on a Unix domain socket instead of TCP when the desktop shell asks for it
+    uds = os.environ.get("QKD_UDS") or None
     
     # Handle shutdown gracefully
     def signal_handler(signum, frame):
@@ -37,18 +42,31 @@ def main():
     signal.signal(signal.SIGTERM, signal_handler)
     
     print(f"🔬 QKD-Lab Backend Server")
-    print(f"   Listening on: http://{host}:{port}")
-    print(f"   API Docs: http://{host}:{port}/docs")
+    if uds:
+        print(f"   Listening on: unix:{uds}")
+    else:
+        print(f"   Listening on: http://{host}:{port}")
+        print(f"   API Docs: http://{host}:{port}/docs")
+    print(f"   Seed: {os.environ.get('QKD_SEED', 'random')}")
     print()
     
-    # Run the server
-    uvicorn.run(
+    # Run the server, announcing readiness once the socket is actually bound
+    class ReadyServer(uvicorn.Server):
+        async def startup(self, sockets=None):
+            await super().startup(sockets=sockets)
+            if self.started:
+                address = f"uds={uds}" if uds else f"port={port}"
+                print(f"{READY_MARKER} {address}", flush=True)
+
+    config = uvicorn.Config(
         app,
         host=host,
         port=port,
-        log_level="info",
+        uds=uds,
+        log_level=os.environ.get("QKD_LOG_LEVEL", "info").lower(),
         access_log=True,
     )
+    ReadyServer(config).run()
 
 
 if __name__ == "__main__":
@@ -2,9 +2,15 @@
 General-purpose utility functions for the QKD simulation engine.
 """
 
+import os
+
 import numpy as np
 from numpy.random import Generator
 
+# Session-wide generator seeded from QKD_SEED, used for requests without their own seed
+_session_seed = os.environ.get("QKD_SEED")
+_session_rng = np.random.default_rng(int(_session_seed)) if _session_seed else None
+
 
 def create_rng(seed: int | None = None) -> Generator:
     """
@@ -15,11 +21,14 @@ def create_rng(seed: int | None = None) -> Generator:
     debugging.
 
     Args:
-        seed: Optional integer seed. ``None`` → non-deterministic.
+        seed: Optional integer seed. ``None`` → derived from the session
+            seed (``QKD_SEED``) if set, otherwise non-deterministic.
 
     Returns:
         A ``numpy.random.Generator`` instance (PCG-64 algorithm).
     """
+    if seed is None and _session_rng is not None:
+        seed = int(_session_rng.integers(2**63))
     return np.random.default_rng(seed)
 
 
@@ -0,0 +1,1453 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::breaker::{BreakerState, CircuitBreaker};
+use crate::config::{url_host, BackendConfig, TlsConfig};
+use crate::LOG_TARGET;
+use crate::sync::LockExt;
+
+// Timeout for one-off informational requests made on behalf of the frontend
+const API_TIMEOUT: Duration = Duration::from_secs(3);
+// Pings give up quickly so a latency indicator never stalls
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+// Round trips above this are reported as slow
+const SLOW_PING_MS: u64 = 250;
+// Upper bounds on a `benchmark` run, so a typo can't flood the backend
+const MAX_BENCHMARK_REQUESTS: u32 = 10_000;
+const MAX_BENCHMARK_CONCURRENCY: u32 = 64;
+// Simulations can take a while, proxied calls get a generous timeout
+const PROXY_TIMEOUT: Duration = Duration::from_secs(120);
+// The first simulation pays for imports and JIT warmup, allow it more than a normal call
+const WARMUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Backend routes the frontend may reach through `backend_request`
+const PROXY_ALLOWED_PATHS: [&str; 5] = ["/simulate", "/sweep", "/monte-carlo", "/health", "/version"];
+/// Backend route serving the output files of runs, as `/results/{session_id}/{name}`
+pub const RESULTS_ROUTE: &str = "/results";
+/// Backend routes `download_backend_file` may fetch from
+const DOWNLOAD_ALLOWED_PATHS: [&str; 1] = [RESULTS_ROUTE];
+// Each self-test check gets this long before it is reported as unknown
+const SELFTEST_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+// Minimum time between two progress reports of a download
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+// Shared by every backend API call, see `guarded`
+static BREAKER: Mutex<CircuitBreaker> = Mutex::new(CircuitBreaker::new());
+
+// Client shared by every request and the URL scheme it was set up for, see `configure_client`
+static CLIENT: Mutex<Option<(reqwest::Client, &'static str)>> = Mutex::new(None);
+
+/// HTTP client shared by health checks, pings and API calls so connections are pooled.
+/// It has no overall timeout of its own, every request sets the one that suits it.
+pub fn http_client() -> reqwest::Client {
+    current_client().0
+}
+
+/// "https" when the backend is configured for TLS, "http" otherwise
+pub fn scheme() -> &'static str {
+    current_client().1
+}
+
+/// Base URL of a backend at `host:port`, e.g. `http://127.0.0.1:8000`
+pub fn base_url(host: &str, port: u16) -> String {
+    base_url_with(scheme(), host, port)
+}
+
+/// `base_url` for the given scheme rather than the configured one
+fn base_url_with(scheme: &str, host: &str, port: u16) -> String {
+    format!("{}://{}:{}", scheme, url_host(host), port)
+}
+
+fn current_client() -> (reqwest::Client, &'static str) {
+    CLIENT
+        .lock_recover()
+        .get_or_insert_with(|| {
+            let tls = TlsConfig::default();
+            (build_client(&tls, None).unwrap_or_default(), tls.scheme())
+        })
+        .clone()
+}
+
+/// Rebuild the shared client for new TLS and proxy settings. Pooled connections are dropped with
+/// the old client, so this is only done when the config changes.
+pub fn configure_client(config: &BackendConfig) -> Result<(), String> {
+    let tls = &config.tls;
+    let proxy = config.proxy();
+    let client = build_client(tls, proxy.as_deref())?;
+    if tls.enabled {
+        log::info!(target: LOG_TARGET, "Connecting to the backend over HTTPS");
+    }
+    if proxy.is_some() {
+        // The URL may carry credentials, so it isn't logged
+        log::info!(target: LOG_TARGET, "Connecting to the backend through a proxy");
+    }
+    *CLIENT.lock_recover() = Some((client, tls.scheme()));
+    Ok(())
+}
+
+/// Check `configure_client` would accept `config`, e.g. that its CA certificate and proxy URL
+/// parse, without replacing the client in use
+pub fn check_client_config(config: &BackendConfig) -> Result<(), String> {
+    build_client(&config.tls, config.proxy().as_deref()).map(|_| ())
+}
+
+/// Build a client for `tls`, going through `proxy` when given. The proxy environment variables
+/// are never read here, `BackendConfig::proxy` has already decided.
+fn build_client(tls: &TlsConfig, proxy: Option<&str>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(API_TIMEOUT)
+        .pool_idle_timeout(Duration::from_secs(30))
+        .no_proxy();
+    if let Some(url) = proxy {
+        let proxy = reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &tls.ca_cert {
+        let pem = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if tls.accept_invalid_certs {
+        log::warn!(
+            target: LOG_TARGET,
+            "TLS certificate verification is DISABLED for backend connections, do not use this outside development"
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Errors from calling the backend's HTTP API, serialized as `{ kind, message }`
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum BackendApiError {
+    /// The request never got a response
+    Unreachable(String),
+    /// The backend accepted the request but didn't answer in time
+    Timeout(String),
+    /// The backend answered but doesn't implement the endpoint
+    NotSupported(String),
+    /// The response was not what we expected
+    InvalidResponse(String),
+    /// The request was refused before being sent
+    Forbidden(String),
+    /// An argument from the frontend was rejected before calling the backend
+    InvalidRequest(String),
+    /// Run parameters were rejected, by us or by the backend, one entry per bad field
+    InvalidParams(Vec<ParamError>),
+    /// Writing the response to disk failed
+    Io(String),
+    /// The caller cancelled the request
+    Cancelled(String),
+}
+
+impl std::fmt::Display for BackendApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreachable(msg) => write!(f, "Backend unreachable: {}", msg),
+            Self::Timeout(msg) => write!(f, "Backend timed out: {}", msg),
+            Self::NotSupported(msg) => write!(f, "Backend does not support {}", msg),
+            Self::InvalidResponse(msg) => write!(f, "Invalid backend response: {}", msg),
+            Self::Forbidden(msg) => write!(f, "Request not allowed: {}", msg),
+            Self::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
+            Self::InvalidParams(errors) => {
+                let errors: Vec<_> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+                write!(f, "Invalid parameters: {}", errors.join("; "))
+            }
+            Self::Io(msg) => write!(f, "I/O error: {}", msg),
+            Self::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
+        }
+    }
+}
+
+/// Why one run parameter was rejected
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParamError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ParamError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Settings for one protocol run started with `start_run`. Unset optional fields take the
+/// backend's defaults.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunParams {
+    pub protocol: String,
+    // Photons Alice sends, which bounds the length of the final key
+    pub photons: u64,
+    // Fiber length in km
+    pub distance: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attenuation: Option<f64>,
+    // Bit-flip probability of the channel
+    #[serde(default)]
+    pub noise: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector_efficiency: Option<f64>,
+    #[serde(default)]
+    pub eve_enabled: bool,
+    // Share of photons Eve intercepts when enabled
+    #[serde(default)]
+    pub eve_probability: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl RunParams {
+    /// Check every field, reporting all problems at once. The bounds are those of the backend's
+    /// `SimulationRequest`. The protocol is only checked when the backend listed its protocols.
+    pub fn validate(&self, protocols: Option<&[ProtocolInfo]>) -> Result<(), BackendApiError> {
+        let mut errors = Vec::new();
+        if let Some(protocols) = protocols {
+            if !protocols.iter().any(|p| p.name.eq_ignore_ascii_case(&self.protocol)) {
+                let known: Vec<_> = protocols.iter().map(|p| p.name.as_str()).collect();
+                errors.push(ParamError::new(
+                    "protocol",
+                    format!("unknown protocol '{}', expected one of {}", self.protocol, known.join(", ")),
+                ));
+            }
+        }
+        if !(100..=10_000_000).contains(&self.photons) {
+            errors.push(ParamError::new("photons", "must be between 100 and 10000000"));
+        }
+        let mut check = |field: &str, value: f64, min: f64, max: f64| {
+            if !(min..=max).contains(&value) {
+                errors.push(ParamError::new(field, format!("must be between {} and {}", min, max)));
+            }
+        };
+        check("distance", self.distance, 0.0, 1000.0);
+        if let Some(attenuation) = self.attenuation {
+            check("attenuation", attenuation, 0.0, 10.0);
+        }
+        check("noise", self.noise, 0.0, 0.5);
+        check("eve_probability", self.eve_probability, 0.0, 1.0);
+        if let Some(efficiency) = self.detector_efficiency {
+            if !(efficiency > 0.0 && efficiency <= 1.0) {
+                errors.push(ParamError::new("detector_efficiency", "must be above 0 and at most 1"));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(BackendApiError::InvalidParams(errors))
+        }
+    }
+}
+
+/// Body of a `POST /runs` response
+#[derive(Deserialize)]
+struct RunStarted {
+    session_id: String,
+}
+
+/// FastAPI's 422 body, `{"detail": [{"loc": ["body", "photons"], "msg": "..."}]}`
+#[derive(Deserialize)]
+struct ValidationDetail {
+    detail: Vec<ValidationItem>,
+}
+
+#[derive(Deserialize)]
+struct ValidationItem {
+    #[serde(default)]
+    loc: Vec<serde_json::Value>,
+    msg: String,
+}
+
+impl ValidationDetail {
+    fn into_errors(self) -> Vec<ParamError> {
+        self.detail
+            .into_iter()
+            .map(|item| {
+                // The last element of `loc` names the field, the ones before it say where it was
+                let field = item.loc.last().map_or_else(String::new, |loc| match loc {
+                    serde_json::Value::String(name) => name.clone(),
+                    other => other.to_string(),
+                });
+                ParamError::new(&field, item.msg)
+            })
+            .collect()
+    }
+}
+
+/// Build information reported by the backend's `/version` endpoint
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackendVersion {
+    pub version: String,
+    pub git_sha: Option<String>,
+    #[serde(default)]
+    pub qkd_protocols: Vec<String>,
+}
+
+/// Protocols and endpoints a particular backend version offers, see `fetch_capabilities`
+#[derive(Clone)]
+pub struct Capabilities {
+    pub version: String,
+    // Lowercased protocol names from `/version`
+    protocols: HashSet<String>,
+    // Route paths from the OpenAPI schema, e.g. `/sweep/param`
+    endpoints: HashSet<String>,
+}
+
+impl Capabilities {
+    pub fn new(version: &BackendVersion, endpoints: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            version: version.version.clone(),
+            protocols: version.qkd_protocols.iter().map(|p| p.to_ascii_lowercase()).collect(),
+            endpoints: endpoints.into_iter().collect(),
+        }
+    }
+
+    /// Whether `feature` is offered: a path like `/sweep/param` is looked up among the
+    /// endpoints, anything else is a protocol name. Unknown features are just unsupported.
+    pub fn supports(&self, feature: &str) -> bool {
+        let feature = feature.trim();
+        if feature.starts_with('/') {
+            self.endpoints.contains(feature)
+        } else {
+            self.protocols.contains(&feature.to_ascii_lowercase())
+        }
+    }
+}
+
+/// Just the routes of the backend's OpenAPI schema
+#[derive(Deserialize)]
+struct OpenApiPaths {
+    #[serde(default)]
+    paths: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A QKD protocol the backend can simulate, from `/protocols`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProtocolInfo {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: Vec<ProtocolParameter>,
+}
+
+/// One request parameter of a protocol
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProtocolParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub description: Option<String>,
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Outcome of one backend self-test check
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// The check didn't complete, e.g. it timed out or the backend couldn't be reached
+    Unknown,
+}
+
+/// One self-test check as reported to the frontend
+#[derive(Clone, Serialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl SelfTestCheck {
+    fn new(name: &str, result: Result<SelfTestResponse, BackendApiError>, duration: Duration) -> Self {
+        let (status, detail) = match result {
+            Ok(resp) if resp.passed => (CheckStatus::Pass, resp.detail),
+            Ok(resp) => (CheckStatus::Fail, resp.detail),
+            Err(e) => (CheckStatus::Unknown, Some(e.to_string())),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            detail,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Result of a full self-test run, passed only if every check passed
+#[derive(Clone, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn new(checks: Vec<SelfTestCheck>) -> Self {
+        Self {
+            passed: !checks.is_empty() && checks.iter().all(|check| check.status == CheckStatus::Pass),
+            checks,
+        }
+    }
+}
+
+/// Body of a `POST /selftest/{name}` response
+#[derive(Deserialize)]
+struct SelfTestResponse {
+    passed: bool,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+/// Body of the backend's `/drain` endpoints
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct DrainStatus {
+    pub draining: bool,
+    // Key exchanges still running
+    pub active: u32,
+}
+
+/// Severity of a preflight finding, ordered from fine to fatal
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreflightStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One runtime prerequisite checked by the backend's `/preflight` endpoint
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub status: PreflightStatus,
+    pub message: String,
+    // What the user can do about a warning or failure
+    #[serde(default)]
+    pub hint: Option<String>,
+}
+
+/// Everything `/preflight` reported, with the worst status among the checks
+#[derive(Clone, Serialize)]
+pub struct PreflightReport {
+    pub status: PreflightStatus,
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    pub fn new(checks: Vec<PreflightCheck>) -> Self {
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .fold(PreflightStatus::Ok, |worst, status| if status > worst { status } else { worst });
+        Self { status, checks }
+    }
+
+    /// What went wrong and how to fix it, `None` unless a check failed outright
+    pub fn failure(&self) -> Option<String> {
+        let failed: Vec<_> = self
+            .checks
+            .iter()
+            .filter(|check| check.status == PreflightStatus::Fail)
+            .map(|check| match &check.hint {
+                Some(hint) => format!("{}: {}. {}", check.name, check.message, hint),
+                None => format!("{}: {}", check.name, check.message),
+            })
+            .collect();
+        (!failed.is_empty()).then(|| format!("Backend preflight failed: {}", failed.join("; ")))
+    }
+}
+
+/// The backend's OpenAPI schema for one backend version, serialized as `{ version, kind, ... }`
+#[derive(Clone, Serialize)]
+pub struct OpenApiSchema {
+    pub version: String,
+    #[serde(flatten)]
+    pub body: SchemaBody,
+}
+
+/// Where the frontend finds the schema itself
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaBody {
+    Inline { schema: serde_json::Value },
+    // Large schemas are written to disk rather than pushed through IPC
+    File { path: PathBuf, size: u64 },
+}
+
+impl OpenApiSchema {
+    /// Keep `schema` inline unless its JSON exceeds `inline_limit` bytes, in which case it is
+    /// written to `openapi-<version>.json` in `dir`
+    pub fn new(
+        version: &str,
+        schema: serde_json::Value,
+        inline_limit: usize,
+        dir: &Path,
+    ) -> Result<Self, BackendApiError> {
+        let json = serde_json::to_vec(&schema).map_err(|e| BackendApiError::InvalidResponse(e.to_string()))?;
+        let body = if json.len() <= inline_limit {
+            SchemaBody::Inline { schema }
+        } else {
+            // Versions come from the backend, keep them from escaping `dir`
+            let name: String = version
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') { c } else { '_' })
+                .collect();
+            let path = dir.join(format!("openapi-{}.json", name));
+            let io_error = |e: std::io::Error| BackendApiError::Io(format!("{}: {}", path.display(), e));
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+            std::fs::write(&path, &json).map_err(io_error)?;
+            SchemaBody::File {
+                size: json.len() as u64,
+                path,
+            }
+        };
+        Ok(Self {
+            version: version.to_string(),
+            body,
+        })
+    }
+}
+
+/// Whether a backend session is still producing key material
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionState {
+    #[default]
+    Active,
+    Completed,
+    /// The run stopped with an error, see `/runs/{id}` for it
+    Failed,
+}
+
+/// Live figures of a QKD session, from `/sessions/{id}/metrics`. Sessions are the runs started
+/// with `start_run`, under the same id.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionMetrics {
+    pub qber: f64,
+    pub sifted_key_rate: f64,
+    pub raw_key_rate: f64,
+    // Seconds since the session started
+    pub elapsed: f64,
+    // Missing on backends that only report running sessions
+    #[serde(default)]
+    pub state: SessionState,
+}
+
+/// A file the backend produced for a session, such as a plot, a CSV export or a key file
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub name: String,
+    pub size: u64,
+    // MIME type, e.g. `image/png` or `text/csv`
+    #[serde(rename = "type")]
+    pub kind: String,
+    // Backend path to hand to `download_backend_file`
+    pub path: String,
+}
+
+/// Body of `/runs/{id}/artifacts`
+#[derive(Deserialize)]
+struct ArtifactList {
+    artifacts: Vec<Artifact>,
+}
+
+/// Levels accepted by the backend's `/loglevel` endpoint
+pub const LOG_LEVELS: [&str; 4] = ["DEBUG", "INFO", "WARNING", "ERROR"];
+
+#[derive(Deserialize)]
+struct LogLevelResponse {
+    level: String,
+}
+
+/// Round-trip time of a `/health` request
+#[derive(Clone, Serialize)]
+pub struct PingResult {
+    pub latency_ms: u64,
+    pub slow: bool,
+}
+
+/// Latency distribution and throughput of a `benchmark` run
+#[derive(Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub requests: u32,
+    pub errors: u32,
+    pub concurrency: u32,
+    // Percentiles over the successful requests, zero if none succeeded
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub requests_per_sec: f64,
+    pub elapsed_ms: u64,
+}
+
+impl BenchmarkReport {
+    pub fn new(mut latencies: Vec<Duration>, errors: u32, concurrency: u32, elapsed: Duration) -> Self {
+        latencies.sort();
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = ((p * latencies.len() as f64).ceil() as usize).max(1);
+            latencies
+                .get(rank - 1)
+                .map_or(0.0, |latency| latency.as_secs_f64() * 1000.0)
+        };
+        let requests = latencies.len() as u32 + errors;
+        Self {
+            requests,
+            errors,
+            concurrency,
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            requests_per_sec: requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+/// A request from the frontend to forward to the backend
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProxyRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Option<serde_json::Value>,
+}
+
+/// The backend's answer, including 4xx/5xx responses so the frontend sees validation errors
+#[derive(Serialize)]
+pub struct ProxyResponse {
+    pub status: u16,
+    pub body: Option<serde_json::Value>,
+}
+
+/// Current state of the API circuit breaker
+pub fn breaker_state() -> BreakerState {
+    BREAKER.lock_recover().state()
+}
+
+/// Close the circuit, e.g. once a health check has seen the backend come back
+pub fn reset_breaker() {
+    BREAKER.lock_recover().record_success();
+}
+
+/// Run a backend call through the circuit breaker: fail fast while it is open, and count
+/// connection failures and timeouts towards opening it
+async fn guarded<T>(call: impl Future<Output = Result<T, BackendApiError>>) -> Result<T, BackendApiError> {
+    if let Err(wait) = BREAKER.lock_recover().allow() {
+        return Err(BackendApiError::Unreachable(format!(
+            "backend is not answering, retrying in {}ms",
+            wait.as_millis()
+        )));
+    }
+    let result = call.await;
+    match &result {
+        Err(BackendApiError::Unreachable(_) | BackendApiError::Timeout(_)) => {
+            BREAKER.lock_recover().record_failure()
+        }
+        // Any answer at all, even an unexpected one, shows the backend is up
+        Ok(_) | Err(BackendApiError::NotSupported(_) | BackendApiError::InvalidResponse(_)) => {
+            BREAKER.lock_recover().record_success()
+        }
+        Err(_) => {}
+    }
+    result
+}
+
+/// GET a JSON endpoint on the backend
+async fn get_json<T: serde::de::DeserializeOwned>(
+    host: &str,
+    port: u16,
+    path: &str,
+) -> Result<T, BackendApiError> {
+    guarded(async {
+        let url = format!("{}{}", base_url(host, port), path);
+        let resp = http_client()
+            .get(&url)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(BackendApiError::NotSupported(path.to_string()));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("{} returned {}", path, resp.status())));
+        }
+
+        resp.json::<T>()
+            .await
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))
+    })
+    .await
+}
+
+/// Fetch the backend's build information
+pub async fn fetch_version(host: &str, port: u16) -> Result<BackendVersion, BackendApiError> {
+    get_json(host, port, "/version").await
+}
+
+/// Fetch the protocols the backend supports, `NotSupported` on backends without `/protocols`
+pub async fn fetch_protocols(host: &str, port: u16) -> Result<Vec<ProtocolInfo>, BackendApiError> {
+    get_json(host, port, "/protocols").await
+}
+
+/// Have the backend refuse new sessions so the running ones can finish, `NotSupported` on
+/// backends without `/drain`
+pub async fn start_drain(host: &str, port: u16) -> Result<DrainStatus, BackendApiError> {
+    guarded(async {
+        let resp = http_client()
+            .post(format!("{}/drain", base_url(host, port)))
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        if matches!(resp.status().as_u16(), 404 | 405) {
+            return Err(BackendApiError::NotSupported("/drain".to_string()));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("/drain returned {}", resp.status())));
+        }
+        resp.json::<DrainStatus>()
+            .await
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))
+    })
+    .await
+}
+
+/// How many sessions a draining backend is still running
+pub async fn fetch_drain_status(host: &str, port: u16) -> Result<DrainStatus, BackendApiError> {
+    get_json(host, port, "/drain").await
+}
+
+/// Ask the backend which runtime prerequisites are missing, `NotSupported` on backends without
+/// `/preflight`
+pub async fn fetch_preflight(host: &str, port: u16) -> Result<PreflightReport, BackendApiError> {
+    get_json(host, port, "/preflight").await.map(PreflightReport::new)
+}
+
+/// Fetch the backend's full OpenAPI schema, `NotSupported` on backends that don't publish one
+pub async fn fetch_openapi_schema(host: &str, port: u16) -> Result<serde_json::Value, BackendApiError> {
+    get_json(host, port, "/openapi.json").await
+}
+
+/// Work out what the backend running `version` offers. Endpoints come from its OpenAPI schema;
+/// a backend that doesn't publish one still reports its protocols.
+pub async fn fetch_capabilities(
+    host: &str,
+    port: u16,
+    version: &BackendVersion,
+) -> Result<Capabilities, BackendApiError> {
+    let endpoints = match get_json::<OpenApiPaths>(host, port, "/openapi.json").await {
+        Ok(schema) => schema.paths.into_iter().map(|(path, _)| path).collect(),
+        Err(BackendApiError::NotSupported(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    Ok(Capabilities::new(version, endpoints))
+}
+
+/// Time a GET of `/health`, independent of the readiness state
+pub async fn ping(host: &str, port: u16) -> Result<PingResult, BackendApiError> {
+    guarded(async {
+        let url = format!("{}/health", base_url(host, port));
+        let started = Instant::now();
+        let resp = http_client()
+            .get(&url)
+            .timeout(PING_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    BackendApiError::Timeout(format!("no response within {}ms", PING_TIMEOUT.as_millis()))
+                } else {
+                    BackendApiError::Unreachable(e.to_string())
+                }
+            })?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("/health returned {}", resp.status())));
+        }
+        Ok(PingResult {
+            latency_ms,
+            slow: latency_ms > SLOW_PING_MS,
+        })
+    })
+    .await
+}
+
+/// Fire `requests` GETs of `/health` at the backend, at most `concurrency` at a time, and
+/// report the latencies. Failed requests are counted rather than failing the run. This
+/// bypasses the circuit breaker: a deliberate stress test shouldn't lock out the UI.
+pub async fn benchmark(
+    host: &str,
+    port: u16,
+    requests: u32,
+    concurrency: u32,
+) -> Result<BenchmarkReport, BackendApiError> {
+    if !(1..=MAX_BENCHMARK_REQUESTS).contains(&requests) {
+        return Err(BackendApiError::InvalidRequest(format!(
+            "number of requests must be between 1 and {}",
+            MAX_BENCHMARK_REQUESTS
+        )));
+    }
+    if !(1..=MAX_BENCHMARK_CONCURRENCY).contains(&concurrency) {
+        return Err(BackendApiError::InvalidRequest(format!(
+            "concurrency must be between 1 and {}",
+            MAX_BENCHMARK_CONCURRENCY
+        )));
+    }
+
+    let url = format!("{}/health", base_url(host, port));
+    let permits = Arc::new(tokio::sync::Semaphore::new(concurrency as usize));
+    let started = Instant::now();
+    let mut tasks = tokio::task::JoinSet::new();
+    for _ in 0..requests {
+        let permit = permits.clone().acquire_owned().await.expect("benchmark semaphore is never closed");
+        let request = http_client().get(&url).timeout(PING_TIMEOUT);
+        tasks.spawn(async move {
+            let sent = Instant::now();
+            let ok = request.send().await.is_ok_and(|resp| resp.status().is_success());
+            drop(permit);
+            ok.then(|| sent.elapsed())
+        });
+    }
+
+    let mut latencies = Vec::with_capacity(requests as usize);
+    let mut errors = 0;
+    while let Some(result) = tasks.join_next().await {
+        match result.ok().flatten() {
+            Some(latency) => latencies.push(latency),
+            None => errors += 1,
+        }
+    }
+    Ok(BenchmarkReport::new(latencies, errors, concurrency, started.elapsed()))
+}
+
+/// Backend path of the output file `name` of a session, downloadable with `download`
+pub fn result_path(session_id: &str, name: &str) -> String {
+    format!("{}/{}/{}", RESULTS_ROUTE, session_id, name)
+}
+
+/// Only allow plain paths under an allowed route, so requests can't be pointed elsewhere
+fn check_path(path: &str, allowed_routes: &[&str]) -> Result<(), BackendApiError> {
+    let route = path.split('?').next().unwrap_or_default();
+    let allowed = route.starts_with('/')
+        && !route.contains("..")
+        && !route.contains("//")
+        && !route.contains('@')
+        && !route.contains('\\')
+        && allowed_routes.iter().any(|prefix| {
+            route == *prefix || route.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+        });
+    if allowed {
+        Ok(())
+    } else {
+        Err(BackendApiError::Forbidden(path.to_string()))
+    }
+}
+
+/// Forward a frontend request to the backend, retrying idempotent requests once
+/// if the connection fails
+pub async fn proxy(host: &str, port: u16, request: ProxyRequest) -> Result<ProxyResponse, BackendApiError> {
+    check_path(&request.path, &PROXY_ALLOWED_PATHS)?;
+    guarded(async {
+        let method = reqwest::Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| BackendApiError::Forbidden(format!("method {}", request.method)))?;
+        let url = format!("{}{}", base_url(host, port), request.path);
+        let attempts = if method.is_idempotent() { 2 } else { 1 };
+
+        let mut last_error = None;
+        for _ in 0..attempts {
+            let mut builder = http_client()
+                .request(method.clone(), &url)
+                .timeout(PROXY_TIMEOUT);
+            if let Some(body) = &request.body {
+                builder = builder.json(body);
+            }
+            let resp = match builder.send().await {
+                Ok(resp) => resp,
+                Err(e) if e.is_timeout() => return Err(BackendApiError::Timeout(e.to_string())),
+                Err(e) => {
+                    last_error = Some(BackendApiError::Unreachable(e.to_string()));
+                    continue;
+                }
+            };
+
+            let status = resp.status().as_u16();
+            let text = resp
+                .text()
+                .await
+                .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))?;
+            let body = if text.is_empty() {
+                None
+            } else {
+                Some(serde_json::from_str(&text).map_err(|e| BackendApiError::InvalidResponse(e.to_string()))?)
+            };
+            return Ok(ProxyResponse { status, body });
+        }
+        Err(last_error.unwrap_or(BackendApiError::Unreachable(url)))
+    })
+    .await
+}
+
+/// Change the running backend's log level, returning the level it reports as effective
+pub async fn set_log_level(host: &str, port: u16, level: &str) -> Result<String, BackendApiError> {
+    guarded(async {
+        let resp = http_client()
+            .post(format!("{}/loglevel", base_url(host, port)))
+            .timeout(API_TIMEOUT)
+            .json(&serde_json::json!({ "level": level }))
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        // Older backends don't have the endpoint, FastAPI answers 405 since other routes exist
+        if matches!(resp.status().as_u16(), 404 | 405) {
+            return Err(BackendApiError::NotSupported("/loglevel".to_string()));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("/loglevel returned {}", resp.status())));
+        }
+        resp.json::<LogLevelResponse>()
+            .await
+            .map(|resp| resp.level)
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))
+    })
+    .await
+}
+
+/// Have the backend re-read its settings from `env` without restarting. `NotSupported` on
+/// backends without `/reload`, which have to be restarted to pick changes up.
+pub async fn reload_config(host: &str, port: u16, env: &BTreeMap<String, String>) -> Result<(), BackendApiError> {
+    guarded(async {
+        let resp = http_client()
+            .post(format!("{}/reload", base_url(host, port)))
+            .timeout(API_TIMEOUT)
+            .json(&serde_json::json!({ "env": env }))
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        if matches!(resp.status().as_u16(), 404 | 405) {
+            return Err(BackendApiError::NotSupported("/reload".to_string()));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("/reload returned {}", resp.status())));
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Reject ids that can't go into a URL path as they are
+fn check_id(kind: &str, id: &str) -> Result<(), BackendApiError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')) {
+        return Err(BackendApiError::InvalidRequest(format!("invalid {} '{}'", kind, id)));
+    }
+    Ok(())
+}
+
+/// Ask the backend to cancel a running job
+pub async fn cancel_job(host: &str, port: u16, job_id: &str) -> Result<(), BackendApiError> {
+    guarded(async {
+        check_id("job id", job_id)?;
+        let path = format!("/jobs/{}/cancel", job_id);
+        let resp = http_client()
+            .post(format!("{}{}", base_url(host, port), path))
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        if matches!(resp.status().as_u16(), 404 | 405) {
+            return Err(BackendApiError::NotSupported(path));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("{} returned {}", path, resp.status())));
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Names of the backend's self-test checks, `NotSupported` on backends without `/selftest`
+pub async fn list_selftests(host: &str, port: u16) -> Result<Vec<String>, BackendApiError> {
+    get_json(host, port, "/selftest").await
+}
+
+/// Run one self-test check. A check that can't be run or times out is `Unknown` rather than
+/// an error, so one stuck check doesn't cost the report for the others.
+pub async fn run_selftest_check(host: &str, port: u16, name: &str) -> SelfTestCheck {
+    let started = Instant::now();
+    let result = guarded(async {
+        check_id("self-test name", name)?;
+        let path = format!("/selftest/{}", name);
+        let resp = http_client()
+            .post(format!("{}{}", base_url(host, port), path))
+            .timeout(SELFTEST_CHECK_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    BackendApiError::Timeout(format!("no result within {}s", SELFTEST_CHECK_TIMEOUT.as_secs()))
+                } else {
+                    BackendApiError::Unreachable(e.to_string())
+                }
+            })?;
+
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("{} returned {}", path, resp.status())));
+        }
+        resp.json::<SelfTestResponse>()
+            .await
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))
+    })
+    .await;
+    SelfTestCheck::new(name, result, started.elapsed())
+}
+
+/// Current metrics of a session, `NotSupported` once the backend no longer knows it
+pub async fn fetch_session_metrics(
+    host: &str,
+    port: u16,
+    session_id: &str,
+) -> Result<SessionMetrics, BackendApiError> {
+    check_id("session id", session_id)?;
+    get_json(host, port, &format!("/sessions/{}/metrics", session_id)).await
+}
+
+/// Files the backend has produced for a run started with `start_run`. A run the backend doesn't
+/// know, e.g. after it restarted, lists whatever is on disk for it; a backend without the route
+/// answers 404 and gets `NotSupported`.
+pub async fn fetch_artifacts(host: &str, port: u16, session_id: &str) -> Result<Vec<Artifact>, BackendApiError> {
+    check_id("session id", session_id)?;
+    let path = format!("/runs/{}/artifacts", session_id);
+    guarded(async {
+        let resp = http_client()
+            .get(format!("{}{}", base_url(host, port), path))
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(BackendApiError::NotSupported(path));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("{} returned {}", path, resp.status())));
+        }
+        resp.json::<ArtifactList>()
+            .await
+            .map(|list| list.artifacts)
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))
+    })
+    .await
+}
+
+/// Start a protocol run in the background, returning the session id to follow it with. Call
+/// `RunParams::validate` first; parameters the backend still rejects come back as `InvalidParams`.
+pub async fn start_run(host: &str, port: u16, params: &RunParams) -> Result<String, BackendApiError> {
+    guarded(async {
+        let resp = http_client()
+            .post(format!("{}/runs", base_url(host, port)))
+            .json(params)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+
+        match resp.status().as_u16() {
+            404 | 405 => return Err(BackendApiError::NotSupported("/runs".to_string())),
+            422 => {
+                let detail = resp
+                    .json::<ValidationDetail>()
+                    .await
+                    .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))?;
+                return Err(BackendApiError::InvalidParams(detail.into_errors()));
+            }
+            _ if !resp.status().is_success() => {
+                return Err(BackendApiError::InvalidResponse(format!("/runs returned {}", resp.status())));
+            }
+            _ => {}
+        }
+        let started = resp
+            .json::<RunStarted>()
+            .await
+            .map_err(|e| BackendApiError::InvalidResponse(e.to_string()))?;
+        check_id("session id", &started.session_id)
+            .map_err(|_| BackendApiError::InvalidResponse(format!("bad session id '{}'", started.session_id)))?;
+        Ok(started.session_id)
+    })
+    .await
+}
+
+/// Run a minimal simulation so the backend loads its QKD engines before the first real request.
+/// The request carries its own seed so it doesn't advance the session RNG.
+pub async fn warmup(host: &str, port: u16) -> Result<(), BackendApiError> {
+    guarded(async {
+        let resp = http_client()
+            .post(format!("{}/simulate", base_url(host, port)))
+            .json(&serde_json::json!({ "photons": 100, "distance": 0.0, "seed": 0 }))
+            .timeout(WARMUP_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    BackendApiError::Timeout(format!("warmup took longer than {}s", WARMUP_TIMEOUT.as_secs()))
+                } else {
+                    BackendApiError::Unreachable(e.to_string())
+                }
+            })?;
+
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("/simulate returned {}", resp.status())));
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Stream a backend file to `dest` chunk by chunk, calling `progress` with the bytes written so
+/// far and the total size when known. The body goes to `<dest>.part` first and is only renamed
+/// into place once complete, a failed or cancelled download leaves nothing behind.
+pub async fn download(
+    host: &str,
+    port: u16,
+    path: &str,
+    dest: &Path,
+    cancel: &CancellationToken,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, BackendApiError> {
+    check_path(path, &DOWNLOAD_ALLOWED_PATHS)?;
+    guarded(async {
+        let mut resp = http_client()
+            .get(format!("{}{}", base_url(host, port), path))
+            .send()
+            .await
+            .map_err(|e| BackendApiError::Unreachable(e.to_string()))?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(BackendApiError::InvalidRequest(format!("{} does not exist on the backend", path)));
+        }
+        if !resp.status().is_success() {
+            return Err(BackendApiError::InvalidResponse(format!("{} returned {}", path, resp.status())));
+        }
+
+        let total = resp.content_length();
+        let mut partial = dest.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        let io_error = |e: std::io::Error| BackendApiError::Io(format!("{}: {}", dest.display(), e));
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&partial).await.map_err(io_error)?;
+            let mut written = 0u64;
+            let mut last_report = Instant::now();
+            progress(0, total);
+            loop {
+                // Cancellation wins over a chunk that is already waiting
+                let chunk = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => return Err(BackendApiError::Cancelled(path.to_string())),
+                    chunk = resp.chunk() => chunk.map_err(|e| BackendApiError::Unreachable(e.to_string()))?,
+                };
+                let Some(chunk) = chunk else {
+                    break;
+                };
+                file.write_all(&chunk).await.map_err(io_error)?;
+                written += chunk.len() as u64;
+                if last_report.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+                    progress(written, total);
+                    last_report = Instant::now();
+                }
+            }
+            file.flush().await.map_err(io_error)?;
+            if total.is_some_and(|total| total != written) {
+                return Err(BackendApiError::InvalidResponse(format!(
+                    "{} ended after {} of {} bytes",
+                    path,
+                    written,
+                    total.unwrap_or_default()
+                )));
+            }
+            tokio::fs::rename(&partial, dest).await.map_err(io_error)?;
+            progress(written, total);
+            Ok(written)
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+        result
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_urls_follow_the_tls_setting() {
+        let plain = TlsConfig::default();
+        let tls = TlsConfig {
+            enabled: true,
+            ..TlsConfig::default()
+        };
+        assert_eq!(base_url_with(plain.scheme(), "127.0.0.1", 8000), "http://127.0.0.1:8000");
+        assert_eq!(base_url_with(tls.scheme(), "127.0.0.1", 8443), "https://127.0.0.1:8443");
+        assert_eq!(base_url_with(tls.scheme(), "::1", 8443), "https://[::1]:8443");
+        assert_eq!(base_url_with(plain.scheme(), "lab.local", 80), "http://lab.local:80");
+    }
+
+    fn capabilities() -> Capabilities {
+        let version = BackendVersion {
+            version: "1.2.0".into(),
+            git_sha: None,
+            qkd_protocols: vec!["bb84".into(), "E91".into()],
+        };
+        Capabilities::new(&version, ["/simulate".to_string(), "/sweep/param".to_string()])
+    }
+
+    #[test]
+    fn known_features_are_supported() {
+        let capabilities = capabilities();
+        assert!(capabilities.supports("bb84"));
+        assert!(capabilities.supports("BB84"));
+        assert!(capabilities.supports("e91"));
+        assert!(capabilities.supports("/sweep/param"));
+    }
+
+    #[test]
+    fn selftest_report_with_mixed_results() {
+        let answer = |passed| -> Result<SelfTestResponse, BackendApiError> {
+            Ok(SelfTestResponse {
+                passed,
+                detail: Some("QBER 0.0000, SECURE".into()),
+            })
+        };
+        let checks = vec![
+            SelfTestCheck::new("rng_balance", answer(true), Duration::from_millis(12)),
+            SelfTestCheck::new("bb84_clean_channel", answer(false), Duration::from_millis(80)),
+            SelfTestCheck::new(
+                "bb84_intercept_resend",
+                Err(BackendApiError::Timeout("no result within 30s".into())),
+                SELFTEST_CHECK_TIMEOUT,
+            ),
+        ];
+        let statuses: Vec<_> = checks.iter().map(|check| check.status).collect();
+        assert_eq!(statuses, [CheckStatus::Pass, CheckStatus::Fail, CheckStatus::Unknown]);
+        assert_eq!(checks[2].duration_ms, 30_000);
+        assert!(checks[2].detail.as_deref().unwrap().contains("timed out"));
+
+        assert!(!SelfTestReport::new(checks).passed);
+    }
+
+    #[test]
+    fn selftest_report_passes_only_when_every_check_does() {
+        let pass = |name| SelfTestCheck::new(name, Ok(SelfTestResponse { passed: true, detail: None }), Duration::ZERO);
+        assert!(SelfTestReport::new(vec![pass("a"), pass("b")]).passed);
+        // Nothing ran, so nothing was shown to work
+        assert!(!SelfTestReport::new(Vec::new()).passed);
+    }
+
+    #[test]
+    fn session_metrics_report_their_state() {
+        let active: SessionMetrics = serde_json::from_str(
+            r#"{"qber": 0.031, "sifted_key_rate": 1250.5, "raw_key_rate": 2500.0, "elapsed": 4.2, "state": "active"}"#,
+        )
+        .unwrap();
+        assert_eq!(active.state, SessionState::Active);
+        assert_eq!(active.qber, 0.031);
+
+        let completed: SessionMetrics = serde_json::from_str(
+            r#"{"qber": 0.029, "sifted_key_rate": 0.0, "raw_key_rate": 0.0, "elapsed": 60.0, "state": "completed"}"#,
+        )
+        .unwrap();
+        assert_eq!(completed.state, SessionState::Completed);
+
+        let failed: SessionMetrics = serde_json::from_str(
+            r#"{"qber": 0.0, "sifted_key_rate": 0.0, "raw_key_rate": 0.0, "elapsed": 0.4, "state": "failed"}"#,
+        )
+        .unwrap();
+        assert_eq!(failed.state, SessionState::Failed);
+
+        // Without a state the session is taken to be running
+        let legacy: SessionMetrics =
+            serde_json::from_str(r#"{"qber": 0.1, "sifted_key_rate": 1.0, "raw_key_rate": 2.0, "elapsed": 1.0}"#)
+                .unwrap();
+        assert_eq!(legacy.state, SessionState::Active);
+    }
+
+    #[test]
+    fn ids_must_be_path_safe() {
+        assert!(check_id("session id", "run-42_b").is_ok());
+        for id in ["", "../health", "a/b", "x?y=1", "caf\u{e9}"] {
+            assert!(check_id("session id", id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn only_result_files_can_be_downloaded() {
+        assert!(check_path(&result_path("5f1c0a9e", "final.key"), &DOWNLOAD_ALLOWED_PATHS).is_ok());
+        for path in ["/files/5f1c0a9e/final.key", "/results/../health", "/resultsx/a", "/health"] {
+            assert!(check_path(path, &DOWNLOAD_ALLOWED_PATHS).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn unknown_features_are_not_supported() {
+        let capabilities = capabilities();
+        assert!(!capabilities.supports("b92"));
+        assert!(!capabilities.supports("/monte-carlo"));
+        assert!(!capabilities.supports(""));
+    }
+
+    fn preflight(body: &str) -> PreflightReport {
+        PreflightReport::new(serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn preflight_pass() {
+        let report = preflight(
+            r#"[{"name":"numpy","status":"ok","message":"numpy 1.26.4","hint":null},
+                {"name":"disk_space","status":"ok","message":"52000 MB free"}]"#,
+        );
+        assert_eq!(report.status, PreflightStatus::Ok);
+        assert_eq!(report.failure(), None);
+    }
+
+    #[test]
+    fn preflight_warn_is_not_a_failure() {
+        let report = preflight(
+            r#"[{"name":"numpy","status":"ok","message":"numpy 1.26.4"},
+                {"name":"disk_space","status":"warn","message":"80 MB free","hint":"Free up disk space"}]"#,
+        );
+        assert_eq!(report.status, PreflightStatus::Warn);
+        assert_eq!(report.failure(), None);
+    }
+
+    #[test]
+    fn preflight_fail_explains_what_to_do() {
+        let report = preflight(
+            r#"[{"name":"disk_space","status":"warn","message":"80 MB free"},
+                {"name":"working_dir","status":"fail","message":"/data is not writable",
+                 "hint":"Choose a writable working directory in the backend settings"}]"#,
+        );
+        assert_eq!(report.status, PreflightStatus::Fail);
+        assert_eq!(
+            report.failure().as_deref(),
+            Some(
+                "Backend preflight failed: working_dir: /data is not writable. \
+                 Choose a writable working directory in the backend settings"
+            )
+        );
+    }
+
+    #[test]
+    fn small_schemas_stay_inline() {
+        let dir = std::env::temp_dir().join("qkd-lab-no-such-schema-dir");
+        let schema = serde_json::json!({"openapi": "3.1.0", "paths": {"/health": {}}});
+        let schema = OpenApiSchema::new("1.4.0", schema, 1024, &dir).unwrap();
+        assert!(matches!(schema.body, SchemaBody::Inline { .. }));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn large_schemas_go_to_a_file() {
+        let dir = std::env::temp_dir().join(format!("qkd-lab-schema-{}", std::process::id()));
+        let schema = serde_json::json!({"openapi": "3.1.0", "paths": {"/simulate": {"x": "y".repeat(64)}}});
+        let schema = OpenApiSchema::new("1.4.0+dev/x", schema, 32, &dir).unwrap();
+        let SchemaBody::File { path, size } = &schema.body else {
+            panic!("expected the schema on disk");
+        };
+        let written = std::fs::read(path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(path.file_name().unwrap(), "openapi-1.4.0_dev_x.json");
+        assert_eq!(written.len() as u64, *size);
+        let parsed: serde_json::Value = serde_json::from_slice(&written).unwrap();
+        assert_eq!(parsed["openapi"], "3.1.0");
+    }
+
+    #[test]
+    fn benchmark_percentiles_use_nearest_rank() {
+        let latencies = (1..=100).map(Duration::from_millis).collect();
+        let report = BenchmarkReport::new(latencies, 2, 4, Duration::from_secs(2));
+        assert_eq!(report.requests, 102);
+        assert_eq!((report.p50_ms, report.p95_ms, report.p99_ms), (50.0, 95.0, 99.0));
+        assert_eq!(report.requests_per_sec, 51.0);
+
+        let failed = BenchmarkReport::new(Vec::new(), 3, 1, Duration::from_millis(10));
+        assert_eq!((failed.requests, failed.errors, failed.p99_ms), (3, 3, 0.0));
+    }
+
+    fn bb84() -> Vec<ProtocolInfo> {
+        serde_json::from_str(r#"[{"name": "bb84", "display_name": "BB84", "description": null}]"#).unwrap()
+    }
+
+    fn run(photons: u64, noise: f64) -> RunParams {
+        serde_json::from_value(serde_json::json!({
+            "protocol": "BB84", "photons": photons, "distance": 25.0, "noise": noise,
+        }))
+        .unwrap()
+    }
+
+    fn rejected_fields(result: Result<(), BackendApiError>) -> Vec<String> {
+        match result {
+            Err(BackendApiError::InvalidParams(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            other => panic!("expected invalid params, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sane_run_params_pass() {
+        assert!(run(10_000, 0.02).validate(Some(&bb84())).is_ok());
+        // Without a protocol list only the numbers are checked
+        let e91 = RunParams {
+            protocol: "e91".into(),
+            ..run(100, 0.0)
+        };
+        assert!(e91.validate(None).is_ok());
+    }
+
+    #[test]
+    fn every_bad_run_param_is_reported() {
+        let mut params = run(50, 0.7);
+        params.protocol = "b92".into();
+        params.eve_probability = 1.5;
+        params.detector_efficiency = Some(0.0);
+        assert_eq!(
+            rejected_fields(params.validate(Some(&bb84()))),
+            ["protocol", "photons", "noise", "eve_probability", "detector_efficiency"]
+        );
+        let mut unset = run(100, 0.0);
+        unset.noise = f64::NAN;
+        assert_eq!(rejected_fields(unset.validate(None)), ["noise"]);
+    }
+
+    #[test]
+    fn backend_validation_errors_name_the_field() {
+        let detail: ValidationDetail = serde_json::from_str(
+            r#"{"detail": [{"loc": ["body", "distance"], "msg": "Input should be less than or equal to 1000", "type": "x"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            detail.into_errors(),
+            [ParamError::new("distance", "Input should be less than or equal to 1000")]
+        );
+    }
+}
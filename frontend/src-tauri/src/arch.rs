@@ -0,0 +1,125 @@
+use serde::Serialize;
+#[cfg(desktop)]
+use std::io::Read;
+#[cfg(desktop)]
+use std::path::Path;
+
+/// The machine the app runs on, as far as it can tell
+#[derive(Clone, Serialize)]
+pub struct HostArch {
+    // Architecture the app itself was built for
+    pub app: &'static str,
+    // Architecture of the hardware, differs from `app` when running translated
+    pub native: &'static str,
+    // Running under Rosetta 2 on Apple silicon
+    pub translated: bool,
+}
+
+pub fn host_arch() -> HostArch {
+    let translated = rosetta_translated();
+    let native = if translated { "aarch64" } else { std::env::consts::ARCH };
+    HostArch {
+        app: std::env::consts::ARCH,
+        native,
+        translated,
+    }
+}
+
+/// Whether this process is an x86_64 build translated by Rosetta 2
+fn rosetta_translated() -> bool {
+    if !cfg!(target_os = "macos") || std::env::consts::ARCH != "x86_64" {
+        return false;
+    }
+    std::process::Command::new("sysctl")
+        .args(["-n", "sysctl.proc_translated"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim() == "1")
+        .unwrap_or(false)
+}
+
+/// Read the CPU architecture from an ELF, Mach-O or PE header, named like
+/// `std::env::consts::ARCH`. Universal Mach-O binaries are reported as "universal".
+#[cfg(desktop)]
+pub fn binary_arch(path: &Path) -> Option<&'static str> {
+    let mut header = [0u8; 512];
+    let len = std::fs::File::open(path).ok()?.read(&mut header).ok()?;
+    let header = &header[..len];
+    let u16_le = |at: usize| Some(u16::from_le_bytes(header.get(at..at + 2)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(header.get(at..at + 4)?.try_into().ok()?));
+
+    match header.get(..4)? {
+        [0x7f, b'E', b'L', b'F'] => match u16_le(18)? {
+            0x03 => Some("x86"),
+            0x3e => Some("x86_64"),
+            0x28 => Some("arm"),
+            0xb7 => Some("aarch64"),
+            _ => None,
+        },
+        [0xcf, 0xfa, 0xed, 0xfe] => match u32_le(4)? {
+            0x0100_0007 => Some("x86_64"),
+            0x0100_000c => Some("aarch64"),
+            _ => None,
+        },
+        [0xca, 0xfe, 0xba, 0xbe] => Some("universal"),
+        [b'M', b'Z', ..] => {
+            let pe = u32_le(0x3c)? as usize;
+            if header.get(pe..pe + 4)? != b"PE\0\0" {
+                return None;
+            }
+            match u16_le(pe + 4)? {
+                0x014c => Some("x86"),
+                0x8664 => Some("x86_64"),
+                0xaa64 => Some("aarch64"),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether a spawn error means the OS refused the binary's format, which for a file that
+/// exists and is executable almost always means it was built for another architecture
+#[cfg(desktop)]
+pub fn is_exec_format_error(error: &str) -> bool {
+    let error = error.to_ascii_lowercase();
+    [
+        "exec format error",
+        "os error 8)",
+        "bad cpu type",
+        "os error 86)",
+        "not a valid win32 application",
+        "os error 193)",
+        "os error 216)",
+    ]
+    .iter()
+    .any(|needle| error.contains(needle))
+}
+
+/// Message for a sidecar the OS refused to run because of its architecture
+#[cfg(desktop)]
+pub fn mismatch_message(binary: Option<&str>, host: &HostArch) -> String {
+    match binary {
+        Some(binary) => format!(
+            "Backend binary architecture doesn't match this machine (backend is {}, this machine is {}); install the build for your platform",
+            binary, host.native
+        ),
+        None => format!(
+            "Backend binary architecture doesn't match this machine ({}); install the build for your platform",
+            host.native
+        ),
+    }
+}
+
+/// Warning for an x86_64 backend on Apple silicon: Rosetta runs it, but slowly, and native
+/// extensions using instructions Rosetta doesn't translate (e.g. AVX) crash at runtime
+#[cfg(desktop)]
+pub fn rosetta_warning(binary: Option<&str>, host: &HostArch) -> Option<String> {
+    if !cfg!(target_os = "macos") || host.native != "aarch64" || binary != Some("x86_64") {
+        return None;
+    }
+    Some(
+        "Backend binary is x86_64 and will run under Rosetta on this Apple silicon Mac; \
+         expect slower simulations and possible crashes (SIGILL) in native code"
+            .to_string(),
+    )
+}
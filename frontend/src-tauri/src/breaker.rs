@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// Consecutive connection failures that open the circuit
+const FAILURE_THRESHOLD: u32 = 3;
+// How long an open circuit fails fast before letting a probe request through
+const COOL_DOWN: Duration = Duration::from_secs(5);
+
+/// State of the breaker as reported in `get_backend_status`
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests go through normally
+    Closed,
+    /// The backend looks down, requests fail immediately
+    Open,
+    /// The cool-down is over, the next request decides whether to close the circuit again
+    HalfOpen,
+}
+
+/// Stops API calls from each waiting out a full timeout while the backend is known to be down.
+/// Only connection failures and timeouts count, an error status still means the backend is up.
+pub struct CircuitBreaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    // The request let through a half-open circuit hasn't failed yet
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    pub const fn new() -> Self {
+        Self {
+            failures: 0,
+            opened_at: None,
+            probing: false,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state_at(Instant::now())
+    }
+
+    fn state_at(&self, now: Instant) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if now.saturating_duration_since(at) < COOL_DOWN => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent now, `Err` with the remaining cool-down otherwise.
+    /// Letting a request through a half-open circuit makes it the probe, and restarts the
+    /// cool-down so other requests keep failing fast until the probe has an answer.
+    pub fn allow(&mut self) -> Result<(), Duration> {
+        self.allow_at(Instant::now())
+    }
+
+    fn allow_at(&mut self, now: Instant) -> Result<(), Duration> {
+        match self.state_at(now) {
+            BreakerState::Closed => Ok(()),
+            BreakerState::HalfOpen => {
+                self.probing = true;
+                self.opened_at = Some(now);
+                Ok(())
+            }
+            BreakerState::Open => {
+                let elapsed = self.opened_at.map(|at| now.saturating_duration_since(at)).unwrap_or_default();
+                Err(COOL_DOWN.saturating_sub(elapsed))
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn record_failure(&mut self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    fn record_failure_at(&mut self, now: Instant) {
+        self.failures += 1;
+        if self.probing || self.failures >= FAILURE_THRESHOLD {
+            self.opened_at = Some(now);
+            self.probing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_failures_open_the_circuit() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        for _ in 1..FAILURE_THRESHOLD {
+            breaker.record_failure_at(start);
+            assert!(breaker.state_at(start) == BreakerState::Closed);
+            assert_eq!(breaker.allow_at(start), Ok(()));
+        }
+        breaker.record_failure_at(start);
+        assert!(breaker.state_at(start) == BreakerState::Open);
+        let later = start + Duration::from_secs(2);
+        assert_eq!(breaker.allow_at(later), Err(COOL_DOWN - Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        for _ in 1..FAILURE_THRESHOLD {
+            breaker.record_failure_at(start);
+        }
+        breaker.record_success();
+        breaker.record_failure_at(start);
+        assert!(breaker.state_at(start) == BreakerState::Closed);
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens_the_circuit() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        for _ in 0..FAILURE_THRESHOLD {
+            breaker.record_failure_at(start);
+        }
+        let cooled = start + COOL_DOWN;
+        assert!(breaker.state_at(cooled) == BreakerState::HalfOpen);
+
+        // One probe goes through, the requests behind it keep failing fast
+        assert_eq!(breaker.allow_at(cooled), Ok(()));
+        assert!(breaker.state_at(cooled) == BreakerState::Open);
+        assert!(breaker.allow_at(cooled + Duration::from_millis(10)).is_err());
+
+        // A failed probe opens the circuit again straight away
+        let failed = cooled + Duration::from_millis(20);
+        breaker.record_failure_at(failed);
+        assert!(breaker.state_at(failed) == BreakerState::Open);
+
+        // A successful one closes it
+        let retried = failed + COOL_DOWN;
+        assert_eq!(breaker.allow_at(retried), Ok(()));
+        breaker.record_success();
+        assert!(breaker.state_at(retried) == BreakerState::Closed);
+        assert_eq!(breaker.allow_at(retried), Ok(()));
+    }
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+/// Why the backend process went away, as far as the exit status can tell us
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitKind {
+    Clean,
+    Error,
+    // Python's exit status for command line usage errors
+    Usage,
+    Interrupted,
+    Terminated,
+    Killed,
+    Crashed,
+    Unknown,
+}
+
+impl ExitKind {
+    /// Classify a process exit from its code and, on Unix, the terminating signal.
+    /// Shells and some runtimes report signal deaths as `128 + signal`, so those are decoded too.
+    pub fn classify(code: Option<i32>, signal: Option<i32>) -> Self {
+        if let Some(signal) = signal {
+            return Self::from_signal(signal);
+        }
+        match code {
+            Some(0) => Self::Clean,
+            Some(2) => Self::Usage,
+            Some(code) if code > 128 && code < 160 => Self::from_signal(code - 128),
+            Some(_) => Self::Error,
+            None => Self::Unknown,
+        }
+    }
+
+    fn from_signal(signal: i32) -> Self {
+        match signal {
+            2 => Self::Interrupted,
+            15 | 1 => Self::Terminated,
+            9 => Self::Killed,
+            4 | 6 | 7 | 8 | 11 => Self::Crashed,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Clean => "Backend exited cleanly",
+            Self::Error => "Backend exited with an error",
+            Self::Usage => "Backend rejected its command line arguments",
+            Self::Interrupted => "Backend was interrupted",
+            Self::Terminated => "Backend was asked to terminate",
+            Self::Killed => "Backend was killed, possibly by the OS for using too much memory",
+            Self::Crashed => "Backend crashed",
+            Self::Unknown => "Backend exited for an unknown reason",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_are_classified() {
+        for (code, kind) in [
+            (Some(0), ExitKind::Clean),
+            (Some(1), ExitKind::Error),
+            (Some(2), ExitKind::Usage),
+            (Some(3), ExitKind::Error),
+            (Some(-1), ExitKind::Error),
+            // Up to 128 is an ordinary code, not a signal
+            (Some(128), ExitKind::Error),
+            (Some(255), ExitKind::Error),
+            (None, ExitKind::Unknown),
+        ] {
+            assert_eq!(ExitKind::classify(code, None), kind, "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn signals_are_classified() {
+        for (signal, kind) in [
+            (1, ExitKind::Terminated),
+            (2, ExitKind::Interrupted),
+            (6, ExitKind::Crashed),
+            (9, ExitKind::Killed),
+            (11, ExitKind::Crashed),
+            (15, ExitKind::Terminated),
+            (10, ExitKind::Unknown),
+        ] {
+            assert_eq!(ExitKind::classify(None, Some(signal)), kind, "signal {}", signal);
+            // Reported by a shell as `128 + signal`
+            assert_eq!(ExitKind::classify(Some(128 + signal), None), kind, "code {}", 128 + signal);
+        }
+        // The signal wins over whatever code came with it
+        assert_eq!(ExitKind::classify(Some(0), Some(9)), ExitKind::Killed);
+        assert_eq!(ExitKind::Killed.describe(), "Backend was killed, possibly by the OS for using too much memory");
+    }
+}
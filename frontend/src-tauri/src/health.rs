@@ -0,0 +1,816 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::api::{self, http_client};
+use crate::config::{duration_ms, env_or, url_host};
+
+/// Endpoints probed by the health check, in order.
+/// `{scheme}`, `{host}` and `{port}` are replaced with the configured backend address.
+const DEFAULT_HEALTH_URLS: [&str; 2] = [
+    "{scheme}://{host}:{port}/health",
+    "{scheme}://{host}:{port}/docs",
+];
+
+/// Response header our spawned backend identifies itself with, see `check_instance`
+pub const INSTANCE_HEADER: &str = "X-QKD-Backend";
+
+/// Tunables for the startup health-check loop
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthCheckConfig {
+    pub urls: Vec<String>,
+    #[serde(rename = "request_timeout_ms", with = "duration_ms")]
+    pub request_timeout: Duration,
+    // Give up once this long has passed since the first probe, however many attempts that took
+    #[serde(rename = "deadline_ms", with = "duration_ms")]
+    pub deadline: Duration,
+    // Wait before the first probe so it doesn't hit a backend still importing its modules.
+    // Up to half as much again is added as jitter.
+    #[serde(rename = "initial_grace_ms", with = "duration_ms")]
+    pub initial_grace: Duration,
+    #[serde(rename = "initial_delay_ms", with = "duration_ms")]
+    pub initial_delay: Duration,
+    #[serde(rename = "max_delay_ms", with = "duration_ms")]
+    pub max_delay: Duration,
+    // Probe over this Unix socket instead of TCP, filled in from `BackendConfig::socket_path`
+    #[serde(skip)]
+    pub socket_path: Option<PathBuf>,
+    // Only accept answers carrying this `INSTANCE_HEADER`, set for a backend we spawned so a
+    // leftover one from a previous run on the same port isn't mistaken for it
+    #[serde(skip)]
+    pub instance_id: Option<String>,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            urls: DEFAULT_HEALTH_URLS.iter().map(|url| url.to_string()).collect(),
+            request_timeout: Duration::from_secs(1),
+            deadline: Duration::from_secs(60),
+            initial_grace: Duration::from_millis(300),
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_millis(2000),
+            socket_path: None,
+            instance_id: None,
+        }
+    }
+}
+
+impl HealthCheckConfig {
+    /// Apply overrides from the environment (e.g. slower CI machines)
+    pub fn apply_env(&mut self) {
+        let urls = std::env::var("QKD_HEALTH_URLS")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|urls| !urls.is_empty());
+        if let Some(urls) = urls {
+            self.urls = urls;
+        }
+        let millis = |key: &str, current: Duration| {
+            Duration::from_millis(env_or(key, current.as_millis() as u64))
+        };
+        self.request_timeout = millis("QKD_HEALTH_TIMEOUT_MS", self.request_timeout);
+        self.deadline = millis("QKD_HEALTH_DEADLINE_MS", self.deadline);
+        self.initial_grace = millis("QKD_HEALTH_INITIAL_GRACE_MS", self.initial_grace);
+        self.initial_delay = millis("QKD_HEALTH_INITIAL_DELAY_MS", self.initial_delay);
+        self.max_delay = millis("QKD_HEALTH_MAX_DELAY_MS", self.max_delay);
+    }
+
+    /// Concrete URLs to probe for a backend listening on `host:port`
+    pub fn urls_for(&self, host: &str, port: u16) -> Vec<String> {
+        self.urls_with_scheme(api::scheme(), host, port)
+    }
+
+    /// `urls_for` with the given scheme rather than the configured one
+    fn urls_with_scheme(&self, scheme: &str, host: &str, port: u16) -> Vec<String> {
+        let host = url_host(host);
+        self.urls
+            .iter()
+            .map(|url| {
+                // Templates saved before `{scheme}` existed follow the configured scheme too
+                url.replacen("http://{host}", "{scheme}://{host}", 1)
+                    .replace("{scheme}", scheme)
+                    .replace("{host}", &host)
+                    .replace("{port}", &port.to_string())
+            })
+            .collect()
+    }
+
+    /// Exponential backoff step, capped at `max_delay`
+    pub fn next_delay(&self, current: Duration) -> Duration {
+        backoff(current, self.max_delay)
+    }
+
+    /// How long to wait before the first probe: `initial_grace` plus `roll` worth of jitter,
+    /// at most half the grace on top
+    pub fn grace_delay(&self, roll: u64) -> Duration {
+        let grace = self.initial_grace.as_millis() as u64;
+        Duration::from_millis(grace + roll % (grace / 2 + 1))
+    }
+
+    /// How many probes the backoff schedule fits into `deadline` after the unjittered grace
+    /// period, ignoring the time the probes themselves take. Only an estimate for progress
+    /// reporting, the deadline is what counts.
+    pub fn expected_attempts(&self) -> u32 {
+        let mut elapsed = self.initial_grace;
+        let mut delay = self.initial_delay;
+        let mut attempts = 1;
+        while !delay.is_zero() && elapsed + delay < self.deadline {
+            elapsed += delay;
+            delay = self.next_delay(delay);
+            attempts += 1;
+        }
+        attempts
+    }
+}
+
+/// Double `current`, capped at `max`
+pub fn backoff(current: Duration, max: Duration) -> Duration {
+    std::cmp::min(current * 2, max)
+}
+
+/// Addresses to probe for a backend configured on `host`. Loopback is tried on both IPv4
+/// and IPv6 because `localhost` may resolve to `::1` while the backend only binds IPv4, or
+/// the other way round; the configured form goes first.
+pub fn probe_hosts(host: &str) -> Vec<String> {
+    match host {
+        "127.0.0.1" => vec!["127.0.0.1".into(), "::1".into()],
+        "::1" => vec!["::1".into(), "127.0.0.1".into()],
+        "localhost" => vec!["127.0.0.1".into(), "::1".into()],
+        other => vec![other.to_string()],
+    }
+}
+
+/// `"ipv4"`, `"ipv6"` or `"hostname"` depending on how `host` is written
+pub fn address_family(host: &str) -> &'static str {
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(_)) => "ipv4",
+        Ok(std::net::IpAddr::V6(_)) => "ipv6",
+        Err(_) => "hostname",
+    }
+}
+
+/// Tunables for the steady-state watchdog that catches a backend which is alive but not answering
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    #[serde(rename = "interval_ms", with = "duration_ms")]
+    pub interval: Duration,
+    // Consecutive failed checks before the backend is declared unresponsive
+    pub max_failures: u32,
+    pub auto_restart: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(10),
+            max_failures: 3,
+            auto_restart: true,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn apply_env(&mut self) {
+        self.enabled = env_or("QKD_BACKEND_WATCHDOG", self.enabled);
+        self.interval = Duration::from_millis(env_or(
+            "QKD_BACKEND_WATCHDOG_INTERVAL_MS",
+            self.interval.as_millis() as u64,
+        ));
+        self.max_failures = env_or("QKD_BACKEND_WATCHDOG_MAX_FAILURES", self.max_failures);
+        self.auto_restart = env_or("QKD_BACKEND_WATCHDOG_AUTO_RESTART", self.auto_restart);
+    }
+}
+
+/// Body returned by the backend's `/health` endpoint
+#[derive(Deserialize)]
+struct HealthResponse {
+    status: String,
+    // Older backends don't report subsystems, treat that as up
+    qkd_engine: Option<bool>,
+}
+
+impl HealthResponse {
+    fn is_ready(&self) -> bool {
+        self.status == "ok" && self.qkd_engine.unwrap_or(true)
+    }
+}
+
+/// Why a health probe failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthError {
+    /// Nothing is accepting connections on the port yet
+    ConnectionRefused,
+    /// The connection or request didn't complete within the request timeout
+    Timeout,
+    /// An endpoint answered with a non-success status
+    BadStatus(u16),
+    /// `/health` answered with a body we couldn't parse
+    InvalidBody(String),
+    /// Any other transport failure
+    Request(String),
+    /// Something answered, but not the backend instance we launched
+    ForeignInstance(Option<String>),
+}
+
+impl std::fmt::Display for HealthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConnectionRefused => write!(f, "connection refused"),
+            Self::Timeout => write!(f, "request timed out"),
+            Self::BadStatus(status) => write!(f, "endpoint returned HTTP {}", status),
+            Self::InvalidBody(msg) => write!(f, "invalid health response: {}", msg),
+            Self::Request(msg) => write!(f, "request failed: {}", msg),
+            Self::ForeignInstance(Some(id)) => write!(f, "port is held by another backend (instance {})", id),
+            Self::ForeignInstance(None) => write!(f, "port is held by a server that isn't our backend"),
+        }
+    }
+}
+
+/// Check the instance id an endpoint answered with against the one we launched, if any
+fn check_instance(config: &HealthCheckConfig, answered: Option<&str>) -> Result<(), HealthError> {
+    match &config.instance_id {
+        Some(expected) if answered != Some(expected.as_str()) => {
+            Err(HealthError::ForeignInstance(answered.map(String::from)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Decide readiness from a `/health` body
+fn parse_health_body(body: &str) -> Result<bool, HealthError> {
+    serde_json::from_str::<HealthResponse>(body)
+        .map(|health| health.is_ready())
+        .map_err(|e| HealthError::InvalidBody(e.to_string()))
+}
+
+fn request_error(e: reqwest::Error) -> HealthError {
+    if e.is_timeout() {
+        HealthError::Timeout
+    } else if e.is_connect() {
+        HealthError::ConnectionRefused
+    } else {
+        HealthError::Request(e.to_string())
+    }
+}
+
+/// A health endpoint answered
+pub struct HealthOk {
+    /// Whether the backend reported itself healthy
+    pub ready: bool,
+    /// Address family that answered, see `address_family`
+    pub family: &'static str,
+}
+
+/// Probe the backend, starting with a cheap TCP connect to each of `probe_hosts` so we
+/// don't wait on HTTP timeouts while nothing is listening yet
+pub async fn perform_health_check(config: &HealthCheckConfig, host: &str, port: u16) -> Result<HealthOk, HealthError> {
+    #[cfg(unix)]
+    if let Some(socket) = &config.socket_path {
+        return uds::perform_health_check(config, socket, host, port).await;
+    }
+
+    let mut connect_error = HealthError::ConnectionRefused;
+    let mut open_host = None;
+    for candidate in probe_hosts(host) {
+        let connect = tokio::net::TcpStream::connect((candidate.as_str(), port));
+        match tokio::time::timeout(config.request_timeout, connect).await {
+            Ok(Ok(_)) => {
+                open_host = Some(candidate);
+                break;
+            }
+            Ok(Err(_)) => connect_error = HealthError::ConnectionRefused,
+            Err(_) => connect_error = HealthError::Timeout,
+        }
+    }
+    let Some(host) = open_host else {
+        return Err(connect_error);
+    };
+    let family = address_family(&host);
+
+    let mut last_error = HealthError::Request("No health endpoints configured".to_string());
+    for url in config.urls_for(&host, port) {
+        let resp = match http_client()
+            .get(&url)
+            .timeout(config.request_timeout)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                last_error = request_error(e);
+                continue;
+            }
+        };
+
+        if !resp.status().is_success() {
+            last_error = HealthError::BadStatus(resp.status().as_u16());
+            continue;
+        }
+        let instance = resp.headers().get(INSTANCE_HEADER).and_then(|value| value.to_str().ok());
+        check_instance(config, instance)?;
+
+        if url.ends_with("/health") {
+            let body = resp.text().await.map_err(request_error)?;
+            return parse_health_body(&body).map(|ready| HealthOk { ready, family });
+        }
+
+        // Other endpoints only tell us the server is up
+        return Ok(HealthOk { ready: true, family });
+    }
+
+    Err(last_error)
+}
+
+/// Outcome of a one-shot connection test, see `check_connection`
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ConnectionCheck {
+    /// `/health` answered, `ready` is false if the backend reported itself unhealthy
+    Ok { latency_ms: u64, ready: bool },
+    /// Something is listening but didn't answer like our backend
+    BadStatus {
+        latency_ms: u64,
+        // HTTP status, `None` for an unparseable body or a foreign instance
+        status: Option<u16>,
+        reason: String,
+    },
+    /// Nothing answered in time
+    Unreachable { reason: String },
+}
+
+/// Probe `host:port` once and sort the answer into reachable, answering badly or unreachable.
+/// Takes the config by value so the caller's own is left alone; instance checks are skipped
+/// since a backend under test was never launched by us.
+pub async fn check_connection(mut config: HealthCheckConfig, host: &str, port: u16) -> ConnectionCheck {
+    config.instance_id = None;
+    let started = std::time::Instant::now();
+    let result = perform_health_check(&config, host, port).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(ok) => ConnectionCheck::Ok {
+            latency_ms,
+            ready: ok.ready,
+        },
+        Err(e @ (HealthError::ConnectionRefused | HealthError::Timeout | HealthError::Request(_))) => {
+            ConnectionCheck::Unreachable { reason: e.to_string() }
+        }
+        Err(e) => ConnectionCheck::BadStatus {
+            latency_ms,
+            status: match e {
+                HealthError::BadStatus(status) => Some(status),
+                _ => None,
+            },
+            reason: e.to_string(),
+        },
+    }
+}
+
+/// The host and port a probe loop aims at, re-read from `read` before every attempt so a
+/// config change mid-loop redirects the probes that follow
+pub struct ProbeTarget<F> {
+    read: F,
+    current: (String, u16),
+}
+
+impl<F: Fn() -> (String, u16)> ProbeTarget<F> {
+    pub fn new(read: F) -> Self {
+        let current = read();
+        Self { read, current }
+    }
+
+    /// Address for the next probe, and whether it differs from the previous one
+    pub fn next(&mut self) -> ((String, u16), bool) {
+        let latest = (self.read)();
+        let changed = latest != self.current;
+        self.current = latest;
+        (self.current.clone(), changed)
+    }
+}
+
+/// Whether the spawned sidecar is still running. The monitor marks it dead when the process
+/// terminates so the startup health loop stops probing a backend that is already gone.
+#[derive(Clone, Default)]
+pub struct ProcessAlive(CancellationToken);
+
+impl ProcessAlive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_dead(&self) {
+        self.0.cancel();
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.0.is_cancelled()
+    }
+
+    /// Resolves once the process has terminated, never for one that keeps running
+    pub async fn died(&self) {
+        self.0.cancelled().await
+    }
+}
+
+/// Sit out the jittered grace period before the first startup probe. False if `cancel` fired
+/// first, in which case the caller should stop probing.
+pub async fn wait_grace(config: &HealthCheckConfig, roll: u64, cancel: &CancellationToken) -> bool {
+    tokio::select! {
+        _ = cancel.cancelled() => false,
+        _ = tokio::time::sleep(config.grace_delay(roll)) => true,
+    }
+}
+
+/// Sit out the backoff `wait` between startup probes. False if `cancel` fired, the process
+/// dying cuts the wait short so the next round notices right away.
+pub async fn wait_retry(wait: Duration, cancel: &CancellationToken, alive: &ProcessAlive) -> bool {
+    tokio::select! {
+        _ = cancel.cancelled() => false,
+        _ = alive.died() => true,
+        _ = tokio::time::sleep(wait) => true,
+    }
+}
+
+/// Probe until the backend reports ready, for confirming an in-place change like a config
+/// reload. Gives up with the last probe's error once `within` has passed.
+pub async fn await_ready(
+    config: &HealthCheckConfig,
+    host: &str,
+    port: u16,
+    within: Duration,
+) -> Result<HealthOk, String> {
+    let deadline = tokio::time::Instant::now() + within;
+    let mut delay = config.initial_delay;
+    loop {
+        let error = match tokio::time::timeout_at(deadline, perform_health_check(config, host, port)).await {
+            Ok(Ok(health)) if health.ready => return Ok(health),
+            Ok(Ok(_)) => "backend reported not ready".to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "no answer".to_string(),
+        };
+        if tokio::time::Instant::now() + delay >= deadline {
+            return Err(format!("Backend not ready within {}s: {}", within.as_secs(), error));
+        }
+        tokio::time::sleep(delay).await;
+        delay = config.next_delay(delay);
+    }
+}
+
+/// Health checks against a backend listening on a Unix domain socket. This speaks just enough
+/// HTTP/1.0 to GET the health URLs, which avoids pulling in a UDS-capable HTTP client.
+#[cfg(unix)]
+mod uds {
+    use std::path::Path;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    use super::{check_instance, parse_health_body, HealthCheckConfig, HealthError, HealthOk, INSTANCE_HEADER};
+
+    pub async fn perform_health_check(
+        config: &HealthCheckConfig,
+        socket: &Path,
+        host: &str,
+        port: u16,
+    ) -> Result<HealthOk, HealthError> {
+        let mut last_error = HealthError::Request("No health endpoints configured".to_string());
+        for url in config.urls_for(host, port) {
+            let request = get(socket, request_path(&url));
+            let (status, instance, body) = match tokio::time::timeout(config.request_timeout, request).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(HealthError::Timeout),
+            };
+            if !(200..300).contains(&status) {
+                last_error = HealthError::BadStatus(status);
+                continue;
+            }
+            check_instance(config, instance.as_deref())?;
+            if url.ends_with("/health") {
+                return parse_health_body(&body).map(|ready| HealthOk { ready, family: "unix" });
+            }
+            return Ok(HealthOk { ready: true, family: "unix" });
+        }
+        Err(last_error)
+    }
+
+    /// The path and query of an `http://host:port/...` URL
+    fn request_path(url: &str) -> &str {
+        let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+        without_scheme.find('/').map_or("/", |i| &without_scheme[i..])
+    }
+
+    /// GET `path`, returning the status, the `INSTANCE_HEADER` value and the body
+    async fn get(socket: &Path, path: &str) -> Result<(u16, Option<String>, String), HealthError> {
+        let mut stream = UnixStream::connect(socket)
+            .await
+            .map_err(|_| HealthError::ConnectionRefused)?;
+        let request = format!("GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path);
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| HealthError::Request(e.to_string()))?;
+
+        let mut raw = Vec::new();
+        stream
+            .read_to_end(&mut raw)
+            .await
+            .map_err(|e| HealthError::Request(e.to_string()))?;
+        let raw = String::from_utf8_lossy(&raw);
+        let (head, body) = raw
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| HealthError::InvalidBody("truncated HTTP response".to_string()))?;
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| HealthError::InvalidBody("malformed HTTP status line".to_string()))?;
+        let instance = head.lines().skip(1).find_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            name.trim().eq_ignore_ascii_case(INSTANCE_HEADER).then(|| value.trim().to_string())
+        });
+        Ok((status, instance, body.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_schedule_fits_the_deadline() {
+        let config = HealthCheckConfig {
+            deadline: Duration::from_secs(10),
+            ..HealthCheckConfig::default()
+        };
+        let attempts = config.expected_attempts();
+
+        // The grace period, then sleeps between the probes. The last probe isn't followed by one.
+        let mut delay = config.initial_delay;
+        let mut waited = config.initial_grace;
+        for _ in 1..attempts {
+            waited += delay;
+            delay = config.next_delay(delay);
+        }
+        assert!(waited < config.deadline);
+        // One more sleep would overrun, so the deadline is used to within a single backoff step
+        assert!(waited + delay >= config.deadline);
+        assert!(config.deadline - waited <= config.max_delay);
+    }
+
+    #[test]
+    fn urls_use_the_configured_port() {
+        let config = HealthCheckConfig::default();
+        assert_eq!(
+            config.urls_for("127.0.0.1", 8123),
+            ["http://127.0.0.1:8123/health", "http://127.0.0.1:8123/docs"]
+        );
+        assert_eq!(config.urls_for("::1", 9000)[0], "http://[::1]:9000/health");
+
+        // Custom templates get the port too, including ones saved before `{scheme}` existed
+        let custom = HealthCheckConfig {
+            urls: vec!["http://{host}:{port}/status".into(), "{scheme}://{host}:{port}/".into()],
+            ..HealthCheckConfig::default()
+        };
+        assert_eq!(custom.urls_for("localhost", 18000), ["http://localhost:18000/status", "http://localhost:18000/"]);
+    }
+
+    #[test]
+    fn urls_are_built_for_both_schemes() {
+        let config = HealthCheckConfig {
+            urls: vec![
+                "{scheme}://{host}:{port}/health".into(),
+                "http://{host}:{port}/docs".into(),
+                "http://status.lab.local/{port}".into(),
+            ],
+            ..HealthCheckConfig::default()
+        };
+        assert_eq!(
+            config.urls_with_scheme("http", "127.0.0.1", 8000),
+            ["http://127.0.0.1:8000/health", "http://127.0.0.1:8000/docs", "http://status.lab.local/8000"]
+        );
+        // Templates written for plain HTTP follow TLS too, unless they point somewhere else
+        assert_eq!(
+            config.urls_with_scheme("https", "::1", 8443),
+            ["https://[::1]:8443/health", "https://[::1]:8443/docs", "http://status.lab.local/8443"]
+        );
+    }
+
+    #[test]
+    fn loopback_is_probed_over_both_families() {
+        let config = HealthCheckConfig::default();
+        for (host, first) in [("127.0.0.1", "ipv4"), ("localhost", "ipv4"), ("::1", "ipv6")] {
+            let hosts = probe_hosts(host);
+            let families: Vec<_> = hosts.iter().map(|host| address_family(host)).collect();
+            assert_eq!(families[0], first, "{}", host);
+            assert!(families.contains(&"ipv4") && families.contains(&"ipv6"), "{}", host);
+
+            let urls: Vec<_> = hosts.iter().flat_map(|host| config.urls_for(host, 8000)).collect();
+            assert!(urls.contains(&"http://127.0.0.1:8000/health".to_string()), "{}", host);
+            assert!(urls.contains(&"http://[::1]:8000/health".to_string()), "{}", host);
+        }
+
+        // Anything else is probed as given
+        assert_eq!(probe_hosts("192.168.1.20"), ["192.168.1.20"]);
+        assert_eq!(probe_hosts("lab.local"), ["lab.local"]);
+        assert_eq!(address_family("lab.local"), "hostname");
+    }
+
+    /// Answer every request on a new socket at `path` with `status` and `body`
+    #[cfg(unix)]
+    fn answer_on_socket(path: &std::path::Path, status: &'static str, body: &'static str) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let _ = std::fs::remove_file(path);
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut chunk = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.0 {}\r\n{}: 4242-uds\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    INSTANCE_HEADER,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn backend_answers_over_a_unix_socket() {
+        let path = std::env::temp_dir().join(format!("qkd-lab-health-{}.sock", std::process::id()));
+        let mut config = HealthCheckConfig {
+            request_timeout: Duration::from_millis(500),
+            socket_path: Some(path.clone()),
+            ..HealthCheckConfig::default()
+        };
+        // The host and port only fill in the URL templates, nothing is probed over TCP
+        async fn probe(config: &HealthCheckConfig) -> Result<HealthOk, HealthError> {
+            perform_health_check(config, "127.0.0.1", 1).await
+        }
+
+        assert_eq!(probe(&config).await.err(), Some(HealthError::ConnectionRefused));
+
+        answer_on_socket(&path, "200 OK", r#"{"status":"ok","qkd_engine":true}"#);
+        let health = probe(&config).await.unwrap();
+        assert!(health.ready);
+        assert_eq!(health.family, "unix");
+        config.instance_id = Some("4242-uds".to_string());
+        assert!(probe(&config).await.unwrap().ready);
+        config.instance_id = Some("7e57".to_string());
+        assert_eq!(probe(&config).await.err(), Some(HealthError::ForeignInstance(Some("4242-uds".into()))));
+        config.instance_id = None;
+
+        answer_on_socket(&path, "200 OK", r#"{"status":"starting","qkd_engine":false}"#);
+        assert!(!probe(&config).await.unwrap().ready);
+        answer_on_socket(&path, "503 Service Unavailable", "");
+        assert_eq!(probe(&config).await.err(), Some(HealthError::BadStatus(503)));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = HealthCheckConfig {
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_millis(1000),
+            ..HealthCheckConfig::default()
+        };
+        let mut delays = vec![config.initial_delay];
+        for _ in 0..6 {
+            delays.push(config.next_delay(*delays.last().unwrap()));
+        }
+        let millis: Vec<_> = delays.iter().map(|delay| delay.as_millis()).collect();
+        assert_eq!(millis, [200, 400, 800, 1000, 1000, 1000, 1000]);
+
+        // A cap below the current delay wins right away
+        assert_eq!(backoff(Duration::from_secs(5), Duration::from_secs(2)), Duration::from_secs(2));
+        assert_eq!(backoff(Duration::ZERO, Duration::from_secs(2)), Duration::ZERO);
+    }
+
+    #[test]
+    fn longer_deadlines_allow_more_attempts() {
+        let short = HealthCheckConfig {
+            deadline: Duration::from_secs(5),
+            ..HealthCheckConfig::default()
+        };
+        let long = HealthCheckConfig {
+            deadline: Duration::from_secs(60),
+            ..HealthCheckConfig::default()
+        };
+        assert!(long.expected_attempts() > short.expected_attempts());
+        assert_eq!(
+            HealthCheckConfig { deadline: Duration::ZERO, ..HealthCheckConfig::default() }.expected_attempts(),
+            1
+        );
+    }
+
+    #[test]
+    fn grace_is_jittered_by_at_most_half() {
+        let config = HealthCheckConfig {
+            initial_grace: Duration::from_millis(300),
+            ..HealthCheckConfig::default()
+        };
+        assert_eq!(config.grace_delay(0), Duration::from_millis(300));
+        assert_eq!(config.grace_delay(150), Duration::from_millis(450));
+        assert_eq!(config.grace_delay(151), Duration::from_millis(300));
+        let none = HealthCheckConfig {
+            initial_grace: Duration::ZERO,
+            ..HealthCheckConfig::default()
+        };
+        assert_eq!(none.grace_delay(12345), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn grace_is_observed_before_the_first_probe() {
+        let config = HealthCheckConfig {
+            initial_grace: Duration::from_millis(100),
+            ..HealthCheckConfig::default()
+        };
+        let started = std::time::Instant::now();
+        assert!(wait_grace(&config, 0, &CancellationToken::new()).await);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        // Shutting down during the grace period doesn't wait it out
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let started = std::time::Instant::now();
+        assert!(!wait_grace(&config, 0, &cancel).await);
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn shutdown_mid_poll_ends_the_backoff() {
+        let cancel = CancellationToken::new();
+        let alive = ProcessAlive::new();
+        let shutdown = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            shutdown.cancel();
+        });
+        let started = std::time::Instant::now();
+        assert!(!wait_retry(Duration::from_secs(30), &cancel, &alive).await);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // Once shut down, later rounds don't wait at all
+        assert!(!wait_retry(Duration::from_secs(30), &cancel, &alive).await);
+
+        // A dead process also ends the wait, but lets the loop go on to report it
+        let alive = ProcessAlive::new();
+        alive.mark_dead();
+        assert!(wait_retry(Duration::from_secs(30), &CancellationToken::new(), &alive).await);
+    }
+
+    #[test]
+    fn health_bodies_decide_readiness() {
+        for (body, ready) in [
+            (r#"{"status":"ok","qkd_engine":true}"#, true),
+            // Older backends don't report the engine
+            (r#"{"status":"ok"}"#, true),
+            (r#"{"status":"ok","qkd_engine":true,"uptime":12.5}"#, true),
+            (r#"{"status":"degraded","qkd_engine":true}"#, false),
+            (r#"{"status":"ok","qkd_engine":false}"#, false),
+            (r#"{"status":"starting","qkd_engine":false}"#, false),
+        ] {
+            assert_eq!(parse_health_body(body), Ok(ready), "{}", body);
+        }
+        for body in ["", "OK", "<html></html>", r#"{"qkd_engine":true}"#, r#"{"status":200}"#, r#"{"status":"ok""#] {
+            assert!(matches!(parse_health_body(body), Err(HealthError::InvalidBody(_))), "{}", body);
+        }
+    }
+
+    #[test]
+    fn only_the_launched_instance_is_accepted() {
+        let launched = HealthCheckConfig {
+            instance_id: Some("3f2a9c".into()),
+            ..HealthCheckConfig::default()
+        };
+        assert_eq!(check_instance(&launched, Some("3f2a9c")), Ok(()));
+        assert_eq!(
+            check_instance(&launched, Some("77b01e")),
+            Err(HealthError::ForeignInstance(Some("77b01e".into())))
+        );
+        assert_eq!(check_instance(&launched, None), Err(HealthError::ForeignInstance(None)));
+
+        // A remote backend wasn't launched by us, whatever answers is accepted
+        assert_eq!(check_instance(&HealthCheckConfig::default(), Some("77b01e")), Ok(()));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::logs::{LogLine, LogStream};
+
+// Prefix marking hook output in the log buffer, so it isn't mistaken for the backend's own
+const OUTPUT_PREFIX: &str = "[pre-start] ";
+
+/// Output and outcome of a `pre_start_command` run
+pub struct HookRun {
+    pub output: Vec<LogLine>,
+    pub result: Result<(), String>,
+}
+
+/// Run `command` through the platform shell with the sidecar's working directory and extra
+/// environment, waiting for it to finish. A hook still running after `timeout` is killed.
+pub fn run_pre_start(
+    command: &str,
+    working_dir: Option<&Path>,
+    env: &BTreeMap<String, String>,
+    timeout: Duration,
+) -> HookRun {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    if let Some(dir) = working_dir {
+        shell.current_dir(dir);
+    }
+    let child = shell
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            return HookRun {
+                output: Vec::new(),
+                result: Err(format!("Failed to run pre-start command '{}': {}", command, e)),
+            }
+        }
+    };
+
+    // Drain both pipes while waiting so a chatty hook can't block on a full one
+    let stdout = child.stdout.take().map(|pipe| collect(pipe, LogStream::Stdout));
+    let stderr = child.stderr.take().map(|pipe| collect(pipe, LogStream::Stderr));
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break Err(format!("Pre-start command '{}' did not finish within {:?}", command, timeout));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => break Err(format!("Failed to wait for pre-start command '{}': {}", command, e)),
+        }
+    };
+
+    // Anything the hook started in the background may hold on to the pipes after a kill,
+    // so only a hook that exited is waited on for the rest of its output
+    let output = match &status {
+        Ok(_) => [stdout, stderr]
+            .into_iter()
+            .flatten()
+            .flat_map(|reader| reader.join().unwrap_or_default())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    let result = status.and_then(|status| {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("Pre-start command '{}' failed ({}), not starting the backend", command, status))
+        }
+    });
+    HookRun { output, result }
+}
+
+/// Read `pipe` to the end on a background thread, one log line per output line
+fn collect(pipe: impl Read + Send + 'static, stream: LogStream) -> std::thread::JoinHandle<Vec<LogLine>> {
+    std::thread::spawn(move || {
+        BufReader::new(pipe)
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| LogLine::new(stream, format!("{}{}", OUTPUT_PREFIX, line)))
+            .collect()
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn lines(run: &HookRun) -> Vec<&str> {
+        run.output.iter().map(|line| line.line.as_str()).collect()
+    }
+
+    #[test]
+    fn successful_hook_lets_the_spawn_go_ahead() {
+        let env = BTreeMap::from([("QKD_DATASET".to_string(), "bb84-runs".to_string())]);
+        let run = run_pre_start("echo mounting $QKD_DATASET", None, &env, TIMEOUT);
+        assert_eq!(run.result, Ok(()));
+        assert_eq!(lines(&run), ["[pre-start] mounting bb84-runs"]);
+    }
+
+    #[test]
+    fn failing_hook_aborts_with_its_output() {
+        let run = run_pre_start("echo 'venv not found' >&2; exit 3", None, &BTreeMap::new(), TIMEOUT);
+        let error = run.result.as_ref().unwrap_err();
+        assert!(error.contains("not starting the backend") && error.contains('3'), "{}", error);
+        assert_eq!(lines(&run), ["[pre-start] venv not found"]);
+        assert_eq!(run.output[0].level, crate::logs::LogLevel::Error);
+    }
+
+    #[test]
+    fn hung_hook_is_killed() {
+        let run = run_pre_start("sleep 30", None, &BTreeMap::new(), Duration::from_millis(200));
+        assert!(run.result.unwrap_err().contains("did not finish"));
+    }
+}
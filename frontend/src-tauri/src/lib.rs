@@ -1,177 +1,3379 @@
-use tauri::Manager;
+mod api;
+mod arch;
+mod breaker;
+mod config;
+mod diagnostics;
+mod exit;
+mod health;
+#[cfg(desktop)]
+mod hook;
+mod logs;
+mod metrics;
+mod profiles;
+mod readiness;
+mod redact;
+mod resources;
+mod session;
+#[cfg(test)]
+mod sidecar_tests;
+mod stream;
+mod sync;
+
+use api::{
+    Artifact, BackendApiError, BackendVersion, BenchmarkReport, Capabilities, CheckStatus, OpenApiSchema, PingResult,
+    PreflightReport, PreflightStatus, ProtocolInfo, ProxyRequest, ProxyResponse, RunParams, SelfTestReport,
+    SessionMetrics, SessionState,
+};
+use breaker::BreakerState;
+use config::{config_path, env_or, generate_seed, load_config, save_config, url_host, BackendConfig, BackendMode};
+use diagnostics::DiagnosticBundle;
+use exit::ExitKind;
+use health::{
+    backoff, perform_health_check, wait_grace, wait_retry, ConnectionCheck, HealthCheckConfig, HealthError, HealthOk,
+    ProbeTarget, ProcessAlive, WatchdogConfig,
+};
+use logs::{CrashLog, LineFilter, LogBuffer, LogFileSink, LogLevel, LogLine, LogStream};
+#[cfg(desktop)]
+use logs::{EarlyStderr, OutputQueue, RepeatFilter};
+use metrics::{StartupMetrics, StartupMetricsReport};
+use profiles::{profiles_path, Profiles};
+use readiness::ReadinessSignals;
+#[cfg(desktop)]
+use redact::Redactor;
+#[cfg(desktop)]
+use resources::ResourceSampler;
+use resources::{ResourceHistory, ResourceUsage};
+use serde::{Deserialize, Serialize};
+use session::{RecordedStep, Recording, ReplaySummary};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sync::LockExt;
+#[cfg(desktop)]
+use tauri::async_runtime::Receiver;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+#[cfg(desktop)]
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+#[cfg(desktop)]
 use tauri_plugin_shell::ShellExt;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+/// Runtime the app runs on. Tests use the mock runtime, so the backend management code can be
+/// driven on a mock app without opening a window.
+#[cfg(not(test))]
+pub(crate) type AppRuntime = tauri::Wry;
+#[cfg(test)]
+pub(crate) type AppRuntime = tauri::test::MockRuntime;
+/// Handle to the app on `AppRuntime`
+pub(crate) type AppHandle = tauri::AppHandle<AppRuntime>;
+
+/// Log target for the app's own backend management messages
+pub(crate) const LOG_TARGET: &str = "qkd_lab::backend";
+/// Log target for lines printed by the backend process itself
+const BACKEND_OUTPUT_TARGET: &str = "qkd_lab::backend_output";
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+// Default first delay and cap for the backoff between automatic restarts
+const DEFAULT_RESTART_BACKOFF_MS: u64 = 500;
+const DEFAULT_RESTART_MAX_BACKOFF_MS: u64 = 10_000;
+// Largest restart delay a policy may ask for
+const MAX_RESTART_DELAY_MS: u64 = 300_000;
+// How long a backend must stay up after becoming ready for its earlier crashes to be forgotten
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+// How many ports above the configured one to try when it is already taken
+const PORT_SCAN_RANGE: u16 = 100;
+// Stderr lines included with a `backend-exited` event
+const EXIT_STDERR_LINES: usize = 10;
+// Stderr lines kept from the start of a run to explain a backend that dies before it is ready
+const STARTUP_STDERR_LINES: usize = 20;
+// Most lines `get_backend_errors` and `get_backend_logs_filtered` return, whatever limit is asked for
+const MAX_ERROR_LINES: usize = 200;
+// How often `watch_session_metrics` polls unless asked otherwise, and the fastest it may
+const DEFAULT_METRICS_INTERVAL_MS: u64 = 1000;
+const MIN_METRICS_INTERVAL_MS: u64 = 100;
+// Sessions whose artifact lists `list_artifacts` keeps at once
+const MAX_CACHED_ARTIFACT_SESSIONS: usize = 64;
+// How often `drain_backend` checks on the sessions it is waiting for
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+// How long `pre_start_command` may run before the spawn is abandoned
+#[cfg(desktop)]
+const PRE_START_TIMEOUT: Duration = Duration::from_secs(120);
+// OpenAPI schemas larger than this are handed to the frontend as a file
+const INLINE_SCHEMA_LIMIT: usize = 512 * 1024;
+// How long a reloaded backend gets to answer `/health` as ready again
+const RELOAD_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+// Per-request timeout cap for `test_connection`, so a dead address fails fast
+const TEST_CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+// Exits remembered for the diagnostic bundle
+const EXIT_HISTORY: usize = 10;
+// How long shutdown waits for the monitor to confirm the killed backend is gone
+const EXIT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(2);
+const SPAWN_RETRY_DELAY_MS: u64 = 250;
+// Store the backend process handle so we can kill it on shutdown
+struct BackendState {
+    // Not available on mobile, where the backend always runs elsewhere
+    #[cfg(desktop)]
+    child: Mutex<Option<CommandChild>>,
+    // Signalled by the monitor once it has seen the current child's `Terminated` event
+    #[cfg(desktop)]
+    exit_rx: Mutex<Option<std::sync::mpsc::Receiver<()>>>,
+    // Lifecycle state, only changed through `set_phase` so every change is emitted
+    phase: Mutex<BackendPhase>,
+    // Most recent failure, cleared once the backend is ready again
+    last_error: Mutex<Option<BackendError>>,
+    // Monitor and health tasks tied to the current child, aborted on restart
+    tasks: Mutex<Vec<tauri::async_runtime::JoinHandle<()>>>,
+    // Cancelled on intentional shutdown so those tasks exit before the child is gone
+    cancel: Mutex<CancellationToken>,
+    // Number of automatic restarts since the last manual (re)start
+    restart_count: Mutex<u32>,
+    // Set while we are intentionally tearing the backend down
+    shutting_down: AtomicBool,
+    // Cleared by `set_log_forwarding` to pause `backend-log` events, output is still recorded
+    log_forwarding: AtomicBool,
+    // Bumped on every spawn so stale restart tasks can detect they were superseded
+    generation: AtomicU64,
+    restart_policy: Mutex<RestartPolicy>,
+    config: Mutex<BackendConfig>,
+    // When the current child was spawned, used to report uptime
+    started_at: Mutex<Option<Instant>>,
+    // PID of the current child, cleared once it terminates
+    pid: Mutex<Option<u32>>,
+    // Memory and CPU samples of the current child, reset on every spawn
+    resources: Mutex<ResourceHistory>,
+    // Recent backend output for the in-app console
+    logs: Mutex<LogBuffer>,
+    // Optional on-disk copy of the backend output
+    log_file: Mutex<Option<LogFileSink>>,
+    // Port the current backend was actually started on, may differ from `config.port`
+    port: Mutex<u16>,
+    // Cached `/version` response for the current backend
+    version: Mutex<Option<BackendVersion>>,
+    // Cached `/protocols` response, static for a given backend so only cleared on respawn
+    protocols: Mutex<Option<Vec<ProtocolInfo>>>,
+    // What the backend offers, kept until a different backend version answers
+    capabilities: Mutex<Option<Capabilities>>,
+    // The backend's OpenAPI schema, likewise kept until a different version answers
+    openapi: Mutex<Option<OpenApiSchema>>,
+    // What the current backend's `/preflight` reported, `None` until it is ready or if it has none
+    preflight: Mutex<Option<PreflightReport>>,
+    // Cancellation tokens of running `download_backend_file` calls, by download id
+    downloads: Mutex<HashMap<String, CancellationToken>>,
+    // Artifacts listed per session id, refreshed on request, dropped when the session finishes
+    // and cleared on respawn
+    artifacts: Mutex<ArtifactCache>,
+    // Cancellation tokens of running `watch_session_metrics` pollers, by session id
+    metric_watches: Mutex<HashMap<String, CancellationToken>>,
+    // `backend_request` calls being recorded by `record_session`, if any
+    recording: Mutex<Option<Recording>>,
+    // Held while a warmup runs so concurrent `warmup_backend` calls wait for it instead of repeating it
+    warmup: tokio::sync::Mutex<()>,
+    // Key-stream bridge, independent of the backend tasks so it survives restarts
+    stream: Mutex<Option<CancellationToken>>,
+    // Latest backend exits, newest last
+    exits: Mutex<VecDeque<BackendExitedPayload>>,
+    // Architecture read from the sidecar binary's header, `None` if unknown or not embedded
+    backend_arch: Mutex<Option<&'static str>>,
+    // RNG seed forwarded to every spawn this session, so restarts reproduce the same run
+    seed: Mutex<u64>,
+    // Identity handed to the backend we spawned and checked on every health answer,
+    // `None` for a remote backend
+    instance_id: Mutex<Option<String>>,
+    // Address family that answered the last successful startup health check
+    address_family: Mutex<Option<&'static str>>,
+    // Readiness signals seen for the current launch, checked against `config.readiness_policy`
+    readiness: Mutex<ReadinessSignals>,
+    // Timing of the current launch for `get_startup_metrics`
+    startup: Mutex<StartupMetrics>,
+}
+
+impl BackendState {
+    /// State before anything was spawned, for `config`
+    fn new(config: BackendConfig) -> Self {
+        Self {
+            #[cfg(desktop)]
+            child: Mutex::new(None),
+            #[cfg(desktop)]
+            exit_rx: Mutex::new(None),
+            phase: Mutex::new(BackendPhase::Stopped),
+            last_error: Mutex::new(None),
+            tasks: Mutex::new(Vec::new()),
+            cancel: Mutex::new(CancellationToken::new()),
+            restart_count: Mutex::new(0),
+            shutting_down: AtomicBool::new(false),
+            log_forwarding: AtomicBool::new(true),
+            generation: AtomicU64::new(0),
+            restart_policy: Mutex::new(RestartPolicy::from_env()),
+            started_at: Mutex::new(None),
+            pid: Mutex::new(None),
+            resources: Mutex::new(ResourceHistory::default()),
+            logs: Mutex::new(LogBuffer::new(config.log_capacity)),
+            log_file: Mutex::new(None),
+            port: Mutex::new(config.port),
+            version: Mutex::new(None),
+            protocols: Mutex::new(None),
+            capabilities: Mutex::new(None),
+            openapi: Mutex::new(None),
+            preflight: Mutex::new(None),
+            startup: Mutex::new(StartupMetrics::default()),
+            seed: Mutex::new(0),
+            instance_id: Mutex::new(None),
+            address_family: Mutex::new(None),
+            backend_arch: Mutex::new(None),
+            readiness: Mutex::new(ReadinessSignals::default()),
+            exits: Mutex::new(VecDeque::new()),
+            stream: Mutex::new(None),
+            downloads: Mutex::new(HashMap::new()),
+            artifacts: Mutex::new(ArtifactCache::default()),
+            metric_watches: Mutex::new(HashMap::new()),
+            recording: Mutex::new(None),
+            warmup: tokio::sync::Mutex::new(()),
+            config: Mutex::new(config),
+        }
+    }
+
+    /// Snapshot of the current config, so the lock isn't held while it is used
+    fn config(&self) -> BackendConfig {
+        self.config.lock_recover().clone()
+    }
+
+    /// Host and port the backend is reached at right now. Tasks read this before every probe
+    /// rather than capturing it, so a corrected address or a port the backend reported is
+    /// picked up by probes already running.
+    fn target(&self) -> (String, u16) {
+        (self.config().host, *self.port.lock_recover())
+    }
+
+    fn phase(&self) -> BackendPhase {
+        *self.phase.lock_recover()
+    }
+
+    /// Whether the backend has been seen ready, including while the watchdog finds it unresponsive
+    fn is_ready(&self) -> bool {
+        matches!(self.phase(), BackendPhase::Ready | BackendPhase::Unresponsive)
+    }
+
+    /// Move to `phase` and emit `backend-state` if that changed anything and the lifecycle
+    /// allows it, see `BackendPhase::can_become`
+    fn set_phase(&self, app: &AppHandle, phase: BackendPhase) {
+        self.transition(app, |_| true, phase);
+    }
+
+    /// Move to `phase` only if `from` accepts the current phase, checked under the same lock
+    /// so two tasks can't both make the transition. Returns whether the phase changed.
+    fn transition(
+        &self,
+        app: &AppHandle,
+        from: impl Fn(BackendPhase) -> bool,
+        phase: BackendPhase,
+    ) -> bool {
+        let previous = {
+            let mut current = self.phase.lock_recover();
+            if *current == phase || !from(*current) || !current.can_become(phase) {
+                return false;
+            }
+            std::mem::replace(&mut *current, phase)
+        };
+        log::debug!(target: LOG_TARGET, "Backend state {:?} -> {:?}", previous, phase);
+        let _ = app.emit("backend-state", BackendStatePayload {
+            state: phase,
+            previous,
+            timestamp: unix_millis(),
+        });
+        true
+    }
+
+    /// Health-check settings for the current backend, only accepting the instance we launched
+    fn health_config(&self, config: &BackendConfig) -> HealthCheckConfig {
+        let mut health = config.health_config();
+        health.instance_id = self.instance_id.lock_recover().clone();
+        health
+    }
+
+    /// Reset `restart_count` if the current launch has been ready for `STABLE_UPTIME` by `now`,
+    /// so a crash after a long healthy run gets the full set of restarts again. Returns the
+    /// count that was forgiven.
+    fn forgive_restarts(&self, now: Instant) -> u32 {
+        let ready_at = self.startup.lock_recover().ready_at;
+        if !ready_at.is_some_and(|ready_at| now.saturating_duration_since(ready_at) >= STABLE_UPTIME) {
+            return 0;
+        }
+        std::mem::take(&mut *self.restart_count.lock_recover())
+    }
+
+    /// Remember why the backend is in trouble, for `get_backend_status`
+    fn record_error(&self, message: impl Into<String>) {
+        *self.last_error.lock_recover() = Some(BackendError {
+            message: message.into(),
+            timestamp: unix_millis(),
+        });
+    }
+
+    /// Record `message` and move to `Failed`
+    fn fail(&self, app: &AppHandle, message: impl Into<String>) {
+        self.record_error(message);
+        self.set_phase(app, BackendPhase::Failed);
+    }
+
+    /// Cancel and abort the tasks tied to the previous backend, returning the token for the
+    /// next one's. Otherwise their loops would keep polling and emitting next to the new ones.
+    fn renew_tasks(&self) -> CancellationToken {
+        let cancel = CancellationToken::new();
+        std::mem::replace(&mut *self.cancel.lock_recover(), cancel.clone()).cancel();
+        for task in self.tasks.lock_recover().drain(..) {
+            task.abort();
+        }
+        cancel
+    }
+
+    /// Remove the child handle, clearing the PID that goes with it
+    #[cfg(desktop)]
+    fn take_child(&self) -> Option<CommandChild> {
+        *self.pid.lock_recover() = None;
+        self.child.lock_recover().take()
+    }
+}
+
+/// Artifact lists of the sessions `list_artifacts` was last asked about, oldest first
+#[derive(Default)]
+struct ArtifactCache(VecDeque<(String, Vec<Artifact>)>);
+
+impl ArtifactCache {
+    fn get(&self, session_id: &str) -> Option<&Vec<Artifact>> {
+        self.0.iter().find(|(id, _)| id == session_id).map(|(_, artifacts)| artifacts)
+    }
+
+    /// Store the list as the newest, evicting the oldest beyond `MAX_CACHED_ARTIFACT_SESSIONS`.
+    /// An evicted session is simply fetched again when asked for.
+    fn insert(&mut self, session_id: String, artifacts: Vec<Artifact>) {
+        self.remove(&session_id);
+        if self.0.len() >= MAX_CACHED_ARTIFACT_SESSIONS {
+            self.0.pop_front();
+        }
+        self.0.push_back((session_id, artifacts));
+    }
+
+    fn remove(&mut self, session_id: &str) {
+        self.0.retain(|(id, _)| id != session_id);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Why the backend last failed, see `BackendState::record_error`
+#[derive(Clone, Serialize)]
+struct BackendError {
+    message: String,
+    timestamp: u64,
+}
+
+/// Lifecycle state reported to the frontend with every `backend-state` event
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BackendPhase {
+    /// The configured pre-start command is running ahead of the spawn
+    PreStart,
+    /// The sidecar process is being started
+    Spawning,
+    /// The process is up (or a remote backend is expected), waiting for it to report ready
+    WaitingForReady,
+    Ready,
+    /// Was ready, but the watchdog has stopped getting answers
+    Unresponsive,
+    /// Waiting to respawn after a crash or on request
+    Restarting,
+    Stopped,
+    /// Couldn't be started, never became ready or ran out of restarts
+    Failed,
+}
+
+impl BackendPhase {
+    /// Whether the lifecycle allows moving on to `next`. A failed backend stays failed until
+    /// it is started again or stopped, so a late readiness signal can't paper over the failure.
+    fn can_become(self, next: BackendPhase) -> bool {
+        match self {
+            BackendPhase::Failed => {
+                matches!(next, BackendPhase::PreStart | BackendPhase::Spawning | BackendPhase::Stopped)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct BackendStatePayload {
+    state: BackendPhase,
+    previous: BackendPhase,
+    timestamp: u64,
+}
+
+/// Snapshot of the backend returned to the frontend by `get_backend_status`
+#[derive(Serialize)]
+struct BackendStatus {
+    phase: BackendPhase,
+    ready: bool,
+    pid: Option<u32>,
+    port: u16,
+    restart_count: u32,
+    uptime_secs: u64,
+    seed: u64,
+    address_family: Option<&'static str>,
+    restart_policy: RestartPolicy,
+    // Whether API calls are currently failing fast
+    circuit: BreakerState,
+    last_error: Option<BackendError>,
+}
+
+/// Where the webview should send API calls, emitted as `backend-url` and returned by `get_backend_url`
+#[derive(Clone, Serialize)]
+struct BackendUrl {
+    scheme: &'static str,
+    host: String,
+    port: u16,
+    mode: BackendMode,
+    // `scheme://host:port`, ready to use as the API base
+    url: String,
+}
+
+/// Emitted as `backend-resource-warning` when the backend's memory use crosses the threshold
+#[derive(Clone, Serialize)]
+struct ResourceWarningPayload {
+    pid: u32,
+    rss_bytes: u64,
+    threshold_bytes: u64,
+}
+
+/// Emitted periodically as `backend-metrics`, one subscription for a live status badge
+#[derive(Clone, Serialize)]
+struct BackendMetricsPayload {
+    state: BackendPhase,
+    // `None` when the backend didn't answer, `circuit` tells whether it is considered down
+    latency_ms: Option<u64>,
+    circuit: BreakerState,
+    uptime_secs: u64,
+    restart_count: u32,
+    last_error: Option<BackendError>,
+}
+
+/// What `set_backend_config` had to do to apply a new config
+#[derive(Serialize)]
+struct ConfigApplied {
+    restarted: bool,
+    // Changed fields that only take effect in a freshly spawned backend
+    restart_fields: Vec<&'static str>,
+}
+
+/// What `clear_backend_logs` removed
+#[derive(Serialize)]
+struct LogsCleared {
+    lines: usize,
+    crash_logs: usize,
+}
+
+/// How `drain_backend` left the running sessions
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DrainOutcome {
+    // Every session finished
+    Drained,
+    // Sessions were still running at `drain_timeout` and will be interrupted
+    TimedOut,
+    // The backend isn't ready or has no `/drain`, so there was nothing to wait for
+    Skipped,
+}
+
+/// Emitted as `backend-drain-progress` while `drain_backend` waits
+#[derive(Clone, Serialize)]
+struct DrainProgressPayload {
+    active: u32,
+    elapsed_ms: u64,
+}
+
+/// How `reload_backend_config` got the backend to pick up its config
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ReloadPath {
+    // The backend re-read it in place through `/reload`
+    Reloaded,
+    // The backend has no `/reload`, so it was respawned
+    Restarted,
+}
+
+/// What `record_session` should do
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SessionAction {
+    Start,
+    Stop,
+}
+
+/// Limits for automatically respawning a crashed backend, adjustable with `set_restart_policy`
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+struct RestartPolicy {
+    enabled: bool,
+    max_restarts: u32,
+    // Delay before the first restart, doubled for each further attempt
+    backoff_ms: u64,
+    // Cap on the doubled delay, jitter included
+    max_backoff_ms: u64,
+    // Up to this much is added at random so several instances don't restart in lockstep
+    jitter_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            backoff_ms: DEFAULT_RESTART_BACKOFF_MS,
+            max_backoff_ms: DEFAULT_RESTART_MAX_BACKOFF_MS,
+            jitter_ms: DEFAULT_RESTART_BACKOFF_MS / 2,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Read the policy from the environment, falling back to defaults
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: env_or("QKD_BACKEND_AUTO_RESTART", defaults.enabled),
+            max_restarts: env_or("QKD_BACKEND_MAX_RESTARTS", defaults.max_restarts),
+            backoff_ms: env_or("QKD_BACKEND_RESTART_BACKOFF_MS", defaults.backoff_ms),
+            max_backoff_ms: env_or("QKD_BACKEND_RESTART_MAX_BACKOFF_MS", defaults.max_backoff_ms),
+            jitter_ms: env_or("QKD_BACKEND_RESTART_JITTER_MS", defaults.jitter_ms),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.backoff_ms == 0 || self.backoff_ms > MAX_RESTART_DELAY_MS {
+            return Err(format!("backoff_ms must be between 1 and {}", MAX_RESTART_DELAY_MS));
+        }
+        if self.max_backoff_ms < self.backoff_ms || self.max_backoff_ms > MAX_RESTART_DELAY_MS {
+            return Err(format!(
+                "max_backoff_ms must be between backoff_ms and {}",
+                MAX_RESTART_DELAY_MS
+            ));
+        }
+        if self.jitter_ms > self.max_backoff_ms {
+            return Err("jitter_ms must not exceed max_backoff_ms".to_string());
+        }
+        Ok(())
+    }
+
+    /// Delay before the given restart attempt (1-based): `backoff_ms` doubled per attempt, plus
+    /// `roll % (jitter_ms + 1)` of jitter, never more than `max_backoff_ms`
+    fn delay_for(&self, attempt: u32, roll: u64) -> Duration {
+        let cap = Duration::from_millis(self.max_backoff_ms);
+        let mut delay = Duration::from_millis(self.backoff_ms).min(cap);
+        for _ in 1..attempt {
+            if delay == cap {
+                break;
+            }
+            delay = backoff(delay, cap);
+        }
+        let jitter = Duration::from_millis(roll % (self.jitter_ms + 1));
+        (delay + jitter).min(cap)
+    }
+
+    /// Count another restart in `restart_count` and return its attempt number (1-based),
+    /// unless the policy rules a restart out
+    fn claim_attempt(&self, restart_count: &mut u32) -> Result<u32, NoRestart> {
+        if !self.enabled {
+            return Err(NoRestart::Disabled);
+        }
+        if *restart_count >= self.max_restarts {
+            return Err(NoRestart::Exhausted);
+        }
+        *restart_count += 1;
+        Ok(*restart_count)
+    }
+}
+
+/// Why a terminated backend isn't respawned, see `RestartPolicy::claim_attempt`
+#[derive(Debug, PartialEq)]
+enum NoRestart {
+    Disabled,
+    // `max_restarts` restarts already happened
+    Exhausted,
+}
+
+/// How `cancel_backend_job` stopped the job
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CancelMethod {
+    /// The backend cancelled the job itself
+    Soft,
+    /// The backend was restarted, dropping every in-flight job
+    Restart,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendReadyPayload {
+    port: u16,
+    method: &'static str,
+    timestamp: u64,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum StartupOutcome {
+    Pending,
+    Ready,
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+struct StartupProgressPayload {
+    attempt: u32,
+    max_attempts: u32,
+    // None once the outcome is final
+    next_delay_ms: Option<u64>,
+    outcome: StartupOutcome,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendUnreachablePayload {
+    attempts: u32,
+    last_error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendPortChangedPayload {
+    requested: u16,
+    port: u16,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendUnresponsivePayload {
+    failures: u32,
+    last_error: String,
+    restarting: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendRestartingPayload {
+    attempt: u32,
+    max_restarts: u32,
+    delay_ms: u64,
+    exit_code: Option<i32>,
+    // First stderr lines of a backend that exited during startup, empty otherwise
+    stderr: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendRestartedPayload {
+    attempt: u32,
+    exit_code: Option<i32>,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadProgressPayload {
+    download_id: String,
+    written: u64,
+    // `None` when the backend didn't send a Content-Length
+    total: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct SelfTestProgressPayload {
+    check: String,
+    index: usize,
+    total: usize,
+    // `None` while the check is running
+    status: Option<CheckStatus>,
+}
+
+#[derive(Clone, Serialize)]
+struct SessionMetricsPayload {
+    session_id: String,
+    // `None` when the session has disappeared from the backend
+    metrics: Option<SessionMetrics>,
+    // Last event for this session, polling has stopped
+    finished: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendWarmPayload {
+    duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendExitedPayload {
+    timestamp: u64,
+    kind: ExitKind,
+    reason: &'static str,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    // Whether we stopped it ourselves
+    intentional: bool,
+    recent_stderr: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendFailedPayload {
+    restart_count: u32,
+    exit_code: Option<i32>,
+    reason: String,
+    // First stderr lines of a backend that exited during startup, empty otherwise
+    stderr: Vec<String>,
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // The environment is applied in `setup`, once the log plugin can record any warnings
+    let config = BackendConfig::default();
+    let builder = tauri::Builder::<AppRuntime>::new();
+
+    // Must be registered first: a second launch hands its arguments to us and exits
+    // before it gets far enough to spawn a competing backend on the same port
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+        log::info!(target: LOG_TARGET, "Another QKD-Lab instance was launched (args: {:?}), focusing this one", argv);
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }));
+
+    builder
+        // Plugins are initialized before `setup` runs, so logging is live before the sidecar
+        // spawns and its earliest output, usually the most telling on a failed start, is kept
+        .plugin(log_plugin())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(BackendState::new(config))
+        .invoke_handler(tauri::generate_handler![
+            restart_backend,
+            shutdown_backend,
+            start_backend,
+            get_backend_status,
+            get_backend_logs,
+            clear_backend_logs,
+            get_backend_log_path,
+            get_backend_config,
+            set_backend_config,
+            get_backend_version,
+            ping_backend,
+            benchmark_backend,
+            get_backend_resource_usage,
+            get_startup_metrics,
+            export_diagnostics,
+            start_stream,
+            stop_stream,
+            backend_request,
+            set_backend_log_level,
+            cancel_backend_job,
+            warmup_backend,
+            get_backend_url,
+            open_backend_docs,
+            record_session,
+            replay_session,
+            list_protocols,
+            start_qkd_run,
+            backend_supports,
+            get_openapi_schema,
+            run_backend_selftest,
+            list_crash_logs,
+            set_restart_policy,
+            download_backend_file,
+            cancel_download,
+            list_artifacts,
+            get_backend_errors,
+            get_backend_logs_filtered,
+            get_session_metrics,
+            watch_session_metrics,
+            stop_session_metrics,
+            reload_backend_config,
+            export_config,
+            import_config,
+            verify_backend_owner,
+            test_connection,
+            set_log_forwarding,
+            get_preflight,
+            drain_backend,
+            list_profiles,
+            get_active_profile,
+            save_profile,
+            delete_profile,
+            switch_profile
+        ])
+        .setup(|app| {
+            // Prefer the last-used config, still letting the environment override it
+            let mut config = config_path(app.handle())
+                .ok()
+                .and_then(|path| load_config(&path))
+                .unwrap_or_default();
+            config.apply_env();
+            // A bad CA file shouldn't keep the app from starting, fix it in the config instead
+            if let Err(e) = api::configure_client(&config) {
+                log::error!(target: LOG_TARGET, "{}", e);
+            }
+            let state = app.state::<BackendState>();
+            state.logs.lock_recover().set_capacity(config.log_capacity);
+            *state.port.lock_recover() = config.port;
+            *state.config.lock_recover() = config;
+
+            let seed = match state.config().seed {
+                Some(seed) => {
+                    log::info!(target: LOG_TARGET, "Backend RNG seed: {}", seed);
+                    seed
+                }
+                None => {
+                    let seed = generate_seed();
+                    log::info!(target: LOG_TARGET, "No seed given, using generated backend RNG seed {}", seed);
+                    seed
+                }
+            };
+            *state.seed.lock_recover() = seed;
+
+            open_log_file(app.handle());
+
+            // Only the primary instance gets this far; spawn_backend still checks the port is free
+            #[cfg(desktop)]
+            log::info!(target: LOG_TARGET, "Running as the primary QKD-Lab instance");
+
+            #[cfg(all(unix, desktop))]
+            watch_exit_signals(app.handle().clone());
+
+            // Start the backend sidecar in the background, keeping the app alive if it can't be
+            // spawned. A slow pre-start command must not hold up the window.
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                match spawn_backend(&handle).await {
+                    Ok(()) => log::info!(target: LOG_TARGET, "QKD-Lab Backend Startup Initiated"),
+                    Err(e) => report_spawn_failure(&handle, e),
+                }
+            });
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                shutdown_on_exit(window.app_handle());
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Quitting from the menu or dock (macOS) or `app.exit` can end the event loop
+            // without the window ever reporting `Destroyed`
+            if let tauri::RunEvent::Exit = event {
+                shutdown_on_exit(app);
+            }
+        });
+}
+
+/// Stop the backend on the way out, however the app is going: its window destroyed, the event
+/// loop exiting or a termination signal. Later calls find nothing running and do nothing.
+fn shutdown_on_exit(app: &AppHandle) {
+    if stop_backend(app) {
+        log::info!(target: LOG_TARGET, "Backend stopped on app exit");
+    }
+}
+
+/// Stop the backend and exit on SIGTERM, SIGINT or SIGHUP (`kill`, Ctrl+C in a dev terminal,
+/// logging out), which would otherwise end the app without any of Tauri's exit hooks running.
+/// SIGKILL can't be caught. Windows has no such signals for a GUI process, and a kill from
+/// Task Manager skips every hook, so there `RunEvent::Exit` covers the normal quit paths only.
+#[cfg(all(unix, desktop))]
+fn watch_exit_signals(app: AppHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tauri::async_runtime::spawn(async move {
+        let signals = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::interrupt()),
+            signal(SignalKind::hangup()),
+        );
+        let (Ok(mut term), Ok(mut int), Ok(mut hup)) = signals else {
+            log::warn!(target: LOG_TARGET, "Could not install signal handlers, a killed app may leave the backend running");
+            return;
+        };
+        let name = tokio::select! {
+            _ = term.recv() => "SIGTERM",
+            _ = int.recv() => "SIGINT",
+            _ = hup.recv() => "SIGHUP",
+        };
+        log::info!(target: LOG_TARGET, "Received {}, stopping the backend before exiting", name);
+        // The graceful stop blocks for up to the grace period
+        let handle = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || shutdown_on_exit(&handle)).await;
+        app.exit(0);
+    });
+}
+
+/// Stop the running backend (if any) and start a fresh sidecar, letting running sessions
+/// finish first when `drain` is set
+#[tauri::command]
+async fn restart_backend(app: AppHandle, drain: Option<bool>) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    if drain.unwrap_or(false) {
+        drain_before_stop(&app).await;
+    }
+
+    // Stop gracefully and wait for the exit to be confirmed, otherwise the old process may
+    // still hold the port and the new one ends up on an alternate port. The graceful sequence
+    // blocks for up to the grace period, keep it off the async workers.
+    let stop_app = app.clone();
+    let stopped = tauri::async_runtime::spawn_blocking(move || stop_backend(&stop_app))
+        .await
+        .map_err(|e| format!("Failed to stop backend: {}", e))?;
+    if stopped {
+        log::info!(target: LOG_TARGET, "Backend process stopped for restart");
+    }
+
+    state.set_phase(&app, BackendPhase::Restarting);
+    *state.restart_count.lock_recover() = 0;
+    state.shutting_down.store(false, Ordering::SeqCst);
+
+    spawn_backend(&app).await?;
+    log::info!(target: LOG_TARGET, "QKD-Lab Backend Restart Initiated");
+    Ok(())
+}
+
+/// Stop the backend but keep the app running, a no-op if it is already stopped. Running
+/// sessions are given the chance to finish first when `drain` is set.
+#[tauri::command]
+async fn shutdown_backend(app: AppHandle, drain: Option<bool>) -> Result<(), String> {
+    if drain.unwrap_or(false) {
+        drain_before_stop(&app).await;
+    }
+    // The graceful sequence blocks for up to the grace period, keep it off the async workers
+    tauri::async_runtime::spawn_blocking(move || {
+        if !stop_backend(&app) {
+            log::info!(target: LOG_TARGET, "Backend already stopped");
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to stop backend: {}", e))
+}
+
+/// Have the backend stop taking new sessions and wait up to `drain_timeout` for the running
+/// ones to finish, emitting `backend-drain-progress` as they do. Stopping or restarting the
+/// backend is left to the caller.
+#[tauri::command]
+async fn drain_backend(app: AppHandle) -> Result<DrainOutcome, String> {
+    let state = app.state::<BackendState>();
+    if !state.is_ready() {
+        return Ok(DrainOutcome::Skipped);
+    }
+    let config = state.config();
+    let port = *state.port.lock_recover();
+    let mut status = match api::start_drain(&config.host, port).await {
+        Ok(status) => status,
+        Err(BackendApiError::NotSupported(_)) => return Ok(DrainOutcome::Skipped),
+        Err(e) => return Err(format!("Failed to drain backend: {}", e)),
+    };
+
+    let started = Instant::now();
+    loop {
+        let _ = app.emit("backend-drain-progress", DrainProgressPayload {
+            active: status.active,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+        if status.active == 0 {
+            log::info!(target: LOG_TARGET, "Backend drained after {:?}", started.elapsed());
+            return Ok(DrainOutcome::Drained);
+        }
+        if started.elapsed() >= config.drain_timeout {
+            log::warn!(
+                target: LOG_TARGET,
+                "Backend still had {} sessions after {:?}, they will be interrupted",
+                status.active,
+                config.drain_timeout
+            );
+            return Ok(DrainOutcome::TimedOut);
+        }
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        status = api::fetch_drain_status(&config.host, port)
+            .await
+            .map_err(|e| format!("Failed to drain backend: {}", e))?;
+    }
+}
+
+/// Drain ahead of a stop or restart, which goes ahead whatever the drain managed
+async fn drain_before_stop(app: &AppHandle) {
+    if let Err(e) = drain_backend(app.clone()).await {
+        log::warn!(target: LOG_TARGET, "{}, stopping it anyway", e);
+    }
+}
+
+/// Start the backend after `shutdown_backend`, a no-op if it is already running
+#[tauri::command]
+async fn start_backend(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    // Anything but stopped or failed means a backend is starting, running or about to restart.
+    // Claiming the start with the phase change keeps two concurrent calls from both spawning.
+    let claimed = state.transition(
+        &app,
+        |phase| matches!(phase, BackendPhase::Stopped | BackendPhase::Failed),
+        BackendPhase::Spawning,
+    );
+    if !claimed {
+        return Ok(());
+    }
+
+    *state.restart_count.lock_recover() = 0;
+    state.shutting_down.store(false, Ordering::SeqCst);
+
+    spawn_backend(&app).await?;
+    log::info!(target: LOG_TARGET, "QKD-Lab Backend Startup Initiated");
+    Ok(())
+}
+
+/// Report the current backend process state
+#[tauri::command]
+fn get_backend_status(state: tauri::State<'_, BackendState>) -> BackendStatus {
+    backend_status(&state)
+}
+
+fn backend_status(state: &BackendState) -> BackendStatus {
+    let pid = *state.pid.lock_recover();
+    let uptime_secs = match (pid, *state.started_at.lock_recover()) {
+        (Some(_), Some(started_at)) => started_at.elapsed().as_secs(),
+        _ => 0,
+    };
+    BackendStatus {
+        phase: state.phase(),
+        ready: state.is_ready(),
+        pid,
+        port: *state.port.lock_recover(),
+        restart_count: *state.restart_count.lock_recover(),
+        uptime_secs,
+        seed: *state.seed.lock_recover(),
+        address_family: *state.address_family.lock_recover(),
+        restart_policy: *state.restart_policy.lock_recover(),
+        circuit: api::breaker_state(),
+        last_error: state.last_error.lock_recover().clone(),
+    }
+}
+
+/// Resolve the URL of the backend in use, taking the auto-selected port into account
+fn backend_url(state: &BackendState) -> BackendUrl {
+    let config = state.config();
+    // A backend bound to all interfaces is still reached over loopback
+    let host = match config.host.as_str() {
+        "0.0.0.0" => "127.0.0.1".to_string(),
+        "::" => "::1".to_string(),
+        _ => config.host,
+    };
+    let port = *state.port.lock_recover();
+    let scheme = api::scheme();
+    BackendUrl {
+        url: format!("{}://{}:{}", scheme, url_host(&host), port),
+        scheme,
+        host,
+        port,
+        mode: config.mode,
+    }
+}
+
+/// Report the URL the frontend should use for backend API calls
+#[tauri::command]
+fn get_backend_url(state: tauri::State<'_, BackendState>) -> BackendUrl {
+    backend_url(&state)
+}
+
+/// Open the backend's Swagger UI in the default browser, on the port it actually bound
+#[tauri::command]
+fn open_backend_docs(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    if !state.is_ready() {
+        return Err("The backend is not ready yet".to_string());
+    }
+    let url = format!("{}/docs", backend_url(&state).url);
+    log::info!(target: LOG_TARGET, "Opening backend docs at {}", url);
+    app.opener()
+        .open_url(&url, None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", url, e))
+}
+
+fn emit_backend_url(app: &AppHandle) {
+    let _ = app.emit("backend-url", backend_url(&app.state::<BackendState>()));
+}
+
+/// Replace the automatic restart policy, applies to the next crash without restarting anything
+#[tauri::command]
+fn set_restart_policy(state: tauri::State<'_, BackendState>, policy: RestartPolicy) -> Result<(), String> {
+    policy.validate()?;
+    log::info!(
+        target: LOG_TARGET,
+        "Restart policy: {}, up to {} restarts, {}ms backoff capped at {}ms, {}ms jitter",
+        if policy.enabled { "enabled" } else { "disabled" },
+        policy.max_restarts,
+        policy.backoff_ms,
+        policy.max_backoff_ms,
+        policy.jitter_ms
+    );
+    *state.restart_policy.lock_recover() = policy;
+    Ok(())
+}
+
+/// Millisecond timings of the current backend launch
+#[tauri::command]
+fn get_startup_metrics(state: tauri::State<'_, BackendState>) -> StartupMetricsReport {
+    state.startup.lock_recover().report()
+}
+
+/// Write a diagnostic bundle (see `DiagnosticBundle`) to `path`, asking the user where
+/// to save it when no path is given. Returns the written path, `None` if the user cancelled.
+#[tauri::command]
+async fn export_diagnostics(app: AppHandle, path: Option<String>) -> Result<Option<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(path) = save_path(&app, path, "qkd-lab-diagnostics.json")? else {
+            return Ok(None);
+        };
+
+        let state = app.state::<BackendState>();
+        let bundle = DiagnosticBundle {
+            generated_at: unix_millis(),
+            app_version: app.package_info().version.to_string(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            host_arch: arch::host_arch(),
+            backend_arch: *state.backend_arch.lock_recover(),
+            status: backend_status(&state),
+            config: state.config(),
+            startup: state.startup.lock_recover().report(),
+            version: state.version.lock_recover().clone(),
+            recent_exits: state.exits.lock_recover().iter().cloned().collect(),
+            logs: state.logs.lock_recover().snapshot(),
+        };
+        bundle.write(&path)?;
+        log::info!(target: LOG_TARGET, "Diagnostics written to {}", path.display());
+        Ok(Some(path.display().to_string()))
+    })
+    .await
+    .map_err(|e| format!("Diagnostics export failed: {}", e))?
+}
+
+/// Use the given path, or ask for one with a save dialog. `None` if the dialog was cancelled.
+/// Blocks on the dialog, so call it from a blocking task.
+fn save_path(
+    app: &AppHandle,
+    path: Option<String>,
+    default_name: &str,
+) -> Result<Option<std::path::PathBuf>, String> {
+    if let Some(path) = path {
+        return Ok(Some(std::path::PathBuf::from(path)));
+    }
+    let chosen = app
+        .dialog()
+        .file()
+        .set_file_name(default_name)
+        .add_filter("JSON", &["json"])
+        .blocking_save_file();
+    match chosen {
+        Some(file) => file.into_path().map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Start or stop recording the calls made through `backend_request`.
+/// Stopping writes the recording to `path`, or asks where to save it, and returns the file written.
+#[tauri::command]
+async fn record_session(
+    app: AppHandle,
+    action: SessionAction,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let state = app.state::<BackendState>();
+    match action {
+        SessionAction::Start => {
+            let backend_version = state.version.lock_recover().as_ref().map(|v| v.version.clone());
+            *state.recording.lock_recover() = Some(Recording {
+                recorded_at: unix_millis(),
+                backend_version,
+                steps: Vec::new(),
+            });
+            log::info!(target: LOG_TARGET, "Recording backend requests");
+            Ok(None)
+        }
+        SessionAction::Stop => {
+            let recording = state
+                .recording
+                .lock_recover()
+                .take()
+                .ok_or_else(|| "No session is being recorded".to_string())?;
+            tauri::async_runtime::spawn_blocking(move || {
+                let Some(path) = save_path(&app, path, "qkd-lab-session.json")? else {
+                    return Ok(None);
+                };
+                recording.write(&path)?;
+                log::info!(
+                    target: LOG_TARGET,
+                    "Recorded {} backend requests to {}",
+                    recording.steps.len(),
+                    path.display()
+                );
+                Ok(Some(path.display().to_string()))
+            })
+            .await
+            .map_err(|e| format!("Saving the recording failed: {}", e))?
+        }
+    }
+}
+
+/// Re-issue a recorded request sequence against the current backend and report which steps
+/// got the same status as when they were recorded
+#[tauri::command]
+async fn replay_session(app: AppHandle, path: String) -> Result<ReplaySummary, String> {
+    let recording = Recording::read(std::path::Path::new(&path))?;
+    log::info!(target: LOG_TARGET, "Replaying {} backend requests from {}", recording.steps.len(), path);
+    let summary = session::replay(&app, recording).await?;
+    log::info!(
+        target: LOG_TARGET,
+        "Replay finished: {} matched, {} failed",
+        summary.matched,
+        summary.failed
+    );
+    Ok(summary)
+}
+
+/// Start bridging the backend's key-stream socket to `qkd-stream` events, a no-op if running
+#[tauri::command]
+fn start_stream(app: AppHandle) {
+    let state = app.state::<BackendState>();
+    let mut stream = state.stream.lock_recover();
+    if stream.is_some() {
+        return;
+    }
+    let cancel = CancellationToken::new();
+    tauri::async_runtime::spawn(stream::run_bridge(app.clone(), state.config().stream, cancel.clone()));
+    *stream = Some(cancel);
+}
+
+/// Stop the key-stream bridge
+#[tauri::command]
+fn stop_stream(state: tauri::State<'_, BackendState>) {
+    // Cancelling rather than aborting lets the bridge close the socket cleanly
+    if let Some(cancel) = state.stream.lock_recover().take() {
+        cancel.cancel();
+    }
+}
+
+/// Path of the active backend log file, if the file sink is enabled
+#[tauri::command]
+fn get_backend_log_path(state: tauri::State<'_, BackendState>) -> Option<String> {
+    state
+        .log_file
+        .lock_recover()
+        .as_ref()
+        .map(|sink| sink.path().display().to_string())
+}
+
+/// App logging: always to a file in the app log directory, and to stdout in debug builds.
+/// The level defaults to Info and can be overridden with `RUST_LOG` (e.g. `RUST_LOG=debug`).
+fn log_plugin() -> tauri::plugin::TauriPlugin<AppRuntime> {
+    use tauri_plugin_log::{Target, TargetKind};
+
+    let mut targets = vec![Target::new(TargetKind::LogDir {
+        file_name: Some("qkd-lab".into()),
+    })];
+    if cfg!(debug_assertions) {
+        targets.push(Target::new(TargetKind::Stdout));
+    }
+    tauri_plugin_log::Builder::new()
+        .targets(targets)
+        .level(env_or("RUST_LOG", log::LevelFilter::Info))
+        .build()
+}
+
+/// Start teeing backend output to the app log directory when enabled in the config, replacing
+/// any sink already open, or stop when disabled
+fn open_log_file(app: &AppHandle) {
+    let state = app.state::<BackendState>();
+    let config = state.config().log_file;
+    if !config.enabled {
+        *state.log_file.lock_recover() = None;
+        return;
+    }
+
+    let sink = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| LogFileSink::spawn(&dir, &config).map_err(|e| e.to_string()));
+    match sink {
+        Ok(sink) => {
+            log::info!(target: LOG_TARGET, "Backend log file: {}", sink.path().display());
+            *state.log_file.lock_recover() = Some(sink);
+        }
+        Err(e) => log::warn!(target: LOG_TARGET, "Backend log file disabled: {}", e),
+    }
+}
+
+/// Move the buffered output of a backend that just died into a `crash-<ts>.log` file,
+/// so the lines leading up to the crash outlive the buffer being reused by the next instance
+#[cfg(desktop)]
+fn save_crash_log(app: &AppHandle, timestamp: u64) {
+    let state = app.state::<BackendState>();
+    let keep = state.config().log_file.max_crash_logs;
+    if keep == 0 {
+        return;
+    }
+    let lines = state.logs.lock_recover().take();
+    if lines.is_empty() {
+        return;
+    }
+    let saved = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| logs::write_crash_log(&dir, timestamp, &lines, keep).map_err(|e| e.to_string()));
+    match saved {
+        Ok(path) => log::info!(target: LOG_TARGET, "Backend output before the crash saved to {}", path.display()),
+        Err(e) => log::warn!(target: LOG_TARGET, "Could not save crash log: {}", e),
+    }
+}
+
+/// List saved crash logs, newest first
+#[tauri::command]
+fn list_crash_logs(app: AppHandle) -> Result<Vec<CrashLog>, String> {
+    let dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+    logs::list_crash_logs(&dir).map_err(|e| format!("Failed to list crash logs: {}", e))
+}
+
+/// Return the config the backend is (or will be) started with
+#[tauri::command]
+fn get_backend_config(state: tauri::State<'_, BackendState>) -> BackendConfig {
+    state.config()
+}
+
+/// Replace the backend config and persist it. Changes the running process depends on restart
+/// it, anything else is applied in place or picked up at the next launch.
+#[tauri::command]
+async fn set_backend_config(app: AppHandle, config: BackendConfig) -> Result<ConfigApplied, String> {
+    let state = app.state::<BackendState>();
+    let current = state.config();
+    let mut restart_fields = config.restart_fields(&current);
+    // A remote backend still being waited for is just probed at the corrected address instead
+    let follow = current.mode == BackendMode::Remote
+        && config.mode == BackendMode::Remote
+        && state.phase() == BackendPhase::WaitingForReady
+        && restart_fields.iter().all(|field| matches!(*field, "host" | "port"));
+    if follow {
+        restart_fields.clear();
+    }
+    store_config(&app, config)?;
+
+    // A stopped backend stays stopped, it picks the changes up when it is started
+    let restart = !restart_fields.is_empty() && state.phase() != BackendPhase::Stopped;
+    if restart {
+        log::info!(target: LOG_TARGET, "Restarting backend to apply {}", restart_fields.join(", "));
+        restart_backend(app.clone(), None).await?;
+    }
+    Ok(ConfigApplied {
+        restarted: restart,
+        restart_fields,
+    })
+}
+
+/// The current config as a JSON snippet for sharing a setup, credentials left out
+#[tauri::command]
+fn export_config(state: tauri::State<'_, BackendState>) -> Result<String, String> {
+    state.config().export()
+}
+
+/// Apply a snippet from `export_config` like `set_backend_config`, restarting the backend only
+/// if a changed field needs it
+#[tauri::command]
+async fn import_config(app: AppHandle, text: String) -> Result<ConfigApplied, String> {
+    let config = BackendConfig::import(&text, &app.state::<BackendState>().config())?;
+    set_backend_config(app, config).await
+}
+
+/// Have the running backend re-read its config (currently its `env`) without respawning it,
+/// keeping its in-memory state, and confirm with a fresh health check. Backends without a
+/// reload endpoint are restarted instead.
+#[tauri::command]
+async fn reload_backend_config(app: AppHandle) -> Result<ReloadPath, String> {
+    let state = app.state::<BackendState>();
+    if !state.is_ready() {
+        return Err("Backend is not ready".to_string());
+    }
+    let config = state.config();
+    let port = *state.port.lock_recover();
+    match api::reload_config(&config.host, port, &config.env).await {
+        Ok(()) => {}
+        Err(BackendApiError::NotSupported(_)) => {
+            log::info!(target: LOG_TARGET, "Backend can't reload its config, restarting it instead");
+            restart_backend(app.clone(), None).await?;
+            return Ok(ReloadPath::Restarted);
+        }
+        Err(e) => return Err(format!("Backend config reload failed: {}", e)),
+    }
+
+    health::await_ready(&state.health_config(&config), &config.host, port, RELOAD_CONFIRM_TIMEOUT)
+        .await
+        .map_err(|e| format!("Backend config reload not confirmed: {}", e))?;
+    log::info!(target: LOG_TARGET, "Backend reloaded its config");
+    Ok(ReloadPath::Reloaded)
+}
+
+/// Validate `config`, persist it and make it the current config. Settings read when needed take
+/// effect right away, the rest once the backend is next spawned.
+fn store_config(app: &AppHandle, config: BackendConfig) -> Result<(), String> {
+    config.validate()?;
+    // Nothing is applied until the config is known to be good and saved
+    api::check_client_config(&config)?;
+    save_config(&config_path(app)?, &config)?;
+    api::configure_client(&config)?;
+
+    let state = app.state::<BackendState>();
+    let current = state.config();
+    let reopen_log_file = config.log_file != current.log_file;
+    // A remote backend is wherever the config says, the running tasks follow via `target`
+    if config.mode == BackendMode::Remote && current.mode == BackendMode::Remote {
+        *state.port.lock_recover() = config.port;
+    }
+    state.logs.lock_recover().set_capacity(config.log_capacity);
+    if let Some(seed) = config.seed {
+        *state.seed.lock_recover() = seed;
+    }
+    *state.config.lock_recover() = config;
+    if reopen_log_file {
+        open_log_file(app);
+    }
+    Ok(())
+}
+
+/// Names of the saved backend profiles
+#[tauri::command]
+fn list_profiles(app: AppHandle) -> Result<Vec<String>, String> {
+    Ok(Profiles::load(&profiles_path(&app)?).names())
+}
+
+/// The profile last switched to, `None` if the config wasn't picked from a profile
+#[tauri::command]
+fn get_active_profile(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(Profiles::load(&profiles_path(&app)?).active)
+}
+
+/// Save `config` as the profile `name`, or the current config if none is given
+#[tauri::command]
+fn save_profile(app: AppHandle, name: String, config: Option<BackendConfig>) -> Result<(), String> {
+    let path = profiles_path(&app)?;
+    let mut profiles = Profiles::load(&path);
+    let config = config.unwrap_or_else(|| app.state::<BackendState>().config());
+    profiles.insert(&name, config)?;
+    profiles.save(&path)
+}
+
+/// Delete the profile `name`, false if there was none. The running backend is left alone.
+#[tauri::command]
+fn delete_profile(app: AppHandle, name: String) -> Result<bool, String> {
+    let path = profiles_path(&app)?;
+    let mut profiles = Profiles::load(&path);
+    let removed = profiles.remove(&name);
+    profiles.save(&path)?;
+    Ok(removed)
+}
+
+/// Stop the backend and start it again under the profile `name`. A port that is already taken
+/// is handled like any launch, by moving on to the next free one.
+#[tauri::command]
+async fn switch_profile(app: AppHandle, name: String) -> Result<(), String> {
+    let path = profiles_path(&app)?;
+    let mut profiles = Profiles::load(&path);
+    let config = profiles.activate(&name)?;
+    // Check before stopping so a broken profile doesn't leave the user without a backend
+    config.validate()?;
+    api::check_client_config(&config)?;
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || stop_backend(&handle))
+        .await
+        .map_err(|e| format!("Failed to stop backend: {}", e))?;
+    if let Err(e) = store_config(&app, config) {
+        // Still under the previous config, bring that backend back rather than none at all
+        log::warn!(target: LOG_TARGET, "Switching to profile '{}' failed, restarting the previous backend: {}", name, e);
+        if let Err(restart) = start_backend(app).await {
+            log::error!(target: LOG_TARGET, "Failed to restart the previous backend: {}", restart);
+        }
+        return Err(e);
+    }
+    profiles.save(&path)?;
+    log::info!(target: LOG_TARGET, "Switched to backend profile '{}'", name);
+    start_backend(app).await
+}
+
+/// Return the backend's build information, cached after the first successful call
+#[tauri::command]
+async fn get_backend_version(app: AppHandle) -> Result<BackendVersion, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let cached = state.version.lock_recover().clone();
+    if let Some(version) = cached {
+        return Ok(version);
+    }
+
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let version = api::fetch_version(&host, port).await?;
+    *state.version.lock_recover() = Some(version.clone());
+    Ok(version)
+}
+
+/// Whether the backend answering on our port is the instance we launched rather than a leftover
+/// from an earlier run. A remote backend wasn't launched by us, so any healthy answer counts.
+#[tauri::command]
+async fn verify_backend_owner(app: AppHandle) -> Result<bool, String> {
+    let state = app.state::<BackendState>();
+    let config = state.config();
+    let port = *state.port.lock_recover();
+    match perform_health_check(&state.health_config(&config), &config.host, port).await {
+        Ok(_) => Ok(true),
+        Err(HealthError::ForeignInstance(instance)) => {
+            log::warn!(target: LOG_TARGET, "Port {} is held by another backend ({:?})", port, instance);
+            Ok(false)
+        }
+        Err(e) => Err(format!("Backend health check failed: {}", e)),
+    }
+}
+
+/// Probe the backend `config` points at once, without touching the running backend or its
+/// config, so connection settings can be tried out before they are applied
+#[tauri::command]
+async fn test_connection(config: BackendConfig) -> Result<ConnectionCheck, String> {
+    config.validate()?;
+    let mut health = config.health_config();
+    health.request_timeout = health.request_timeout.min(TEST_CONNECTION_TIMEOUT);
+    let check = health::check_connection(health, &config.host, config.port).await;
+    log::debug!(target: LOG_TARGET, "Connection test against {}:{}: {:?}", config.host, config.port, check);
+    Ok(check)
+}
+
+/// Latest memory and CPU sample of the embedded backend and the recent history before it.
+/// Empty for a remote backend, which has no local process to sample.
+#[tauri::command]
+fn get_backend_resource_usage(state: tauri::State<'_, BackendState>) -> ResourceUsage {
+    state.resources.lock_recover().usage()
+}
+
+/// Measure the round-trip latency of a backend health request
+#[tauri::command]
+async fn ping_backend(app: AppHandle) -> Result<PingResult, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    api::ping(&host, port).await
+}
+
+/// Hit `/health` `n_requests` times, at most `concurrency` at once, and report latency
+/// percentiles and throughput. The result is also emitted as `benchmark-complete`.
+#[tauri::command]
+async fn benchmark_backend(
+    app: AppHandle,
+    n_requests: u32,
+    concurrency: u32,
+) -> Result<BenchmarkReport, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let report = api::benchmark(&host, port, n_requests, concurrency).await?;
+    log::info!(
+        target: LOG_TARGET,
+        "Backend benchmark: {} requests ({} failed) at {:.1}/s, p50 {:.1}ms, p95 {:.1}ms, p99 {:.1}ms",
+        report.requests,
+        report.errors,
+        report.requests_per_sec,
+        report.p50_ms,
+        report.p95_ms,
+        report.p99_ms
+    );
+    let _ = app.emit("benchmark-complete", report.clone());
+    Ok(report)
+}
+
+/// List the QKD protocols the backend supports, cached until the backend is respawned
+#[tauri::command]
+async fn list_protocols(app: AppHandle) -> Result<Vec<ProtocolInfo>, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let cached = state.protocols.lock_recover().clone();
+    if let Some(protocols) = cached {
+        return Ok(protocols);
+    }
+
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let protocols = api::fetch_protocols(&host, port).await?;
+    *state.protocols.lock_recover() = Some(protocols.clone());
+    Ok(protocols)
+}
+
+/// Start a protocol run with `params` after checking them against the backend's protocols and
+/// the parameter bounds, returning the session id to follow it with
+#[tauri::command]
+async fn start_qkd_run(app: AppHandle, params: RunParams) -> Result<String, BackendApiError> {
+    let protocols = match list_protocols(app.clone()).await {
+        Ok(protocols) => Some(protocols),
+        Err(BackendApiError::NotSupported(_)) => None,
+        Err(e) => return Err(e),
+    };
+    params.validate(protocols.as_deref())?;
+
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let session_id = api::start_run(&host, port, &params).await?;
+    log::info!(target: LOG_TARGET, "Started {} run {}", params.protocol, session_id);
+    Ok(session_id)
+}
+
+/// Whether the backend offers `feature`, a protocol name like `e91` or an endpoint like
+/// `/sweep/param`. Unknown features are `false` rather than an error so the UI can hide them.
+#[tauri::command]
+async fn backend_supports(app: AppHandle, feature: String) -> Result<bool, BackendApiError> {
+    let version = get_backend_version(app.clone()).await?;
+    let state = app.state::<BackendState>();
+    let cached = state
+        .capabilities
+        .lock_recover()
+        .clone()
+        .filter(|capabilities| capabilities.version == version.version);
+    let capabilities = match cached {
+        Some(capabilities) => capabilities,
+        None => {
+            let host = state.config().host;
+            let port = *state.port.lock_recover();
+            let capabilities = api::fetch_capabilities(&host, port, &version).await?;
+            *state.capabilities.lock_recover() = Some(capabilities.clone());
+            capabilities
+        }
+    };
+    Ok(capabilities.supports(&feature))
+}
+
+/// Return the backend's OpenAPI schema, cached until a different backend version answers.
+/// Schemas too large for IPC are written to the app cache directory and returned as a path.
+#[tauri::command]
+async fn get_openapi_schema(app: AppHandle) -> Result<OpenApiSchema, BackendApiError> {
+    let version = get_backend_version(app.clone()).await?;
+    let state = app.state::<BackendState>();
+    let cached = state
+        .openapi
+        .lock_recover()
+        .clone()
+        .filter(|schema| schema.version == version.version);
+    if let Some(schema) = cached {
+        return Ok(schema);
+    }
+
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let schema = api::fetch_openapi_schema(&host, port).await?;
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| BackendApiError::Io(format!("Failed to resolve app cache directory: {}", e)))?;
+    let schema = OpenApiSchema::new(&version.version, schema, INLINE_SCHEMA_LIMIT, &dir)?;
+    *state.openapi.lock_recover() = Some(schema.clone());
+    Ok(schema)
+}
+
+/// Forward an API call to the backend so the frontend never needs its address
+#[tauri::command]
+async fn backend_request(app: AppHandle, request: ProxyRequest) -> Result<ProxyResponse, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let recorded = state.recording.lock_recover().is_some().then(|| request.clone());
+    let result = api::proxy(&host, port, request).await;
+    if let Some(request) = recorded {
+        let status = result.as_ref().ok().map(|resp| resp.status);
+        if let Some(recording) = state.recording.lock_recover().as_mut() {
+            recording.steps.push(RecordedStep { request, status });
+        }
+    }
+    result
+}
+
+/// Run the backend's self-test checks one at a time, emitting `backend-selftest-progress` as
+/// each starts and finishes. Checks that time out are reported as unknown, not as an error.
+#[tauri::command]
+async fn run_backend_selftest(app: AppHandle) -> Result<SelfTestReport, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let names = api::list_selftests(&host, port).await?;
+    let total = names.len();
+
+    let mut checks = Vec::with_capacity(total);
+    for (index, name) in names.into_iter().enumerate() {
+        let progress = |status| SelfTestProgressPayload {
+            check: name.clone(),
+            index,
+            total,
+            status,
+        };
+        let _ = app.emit("backend-selftest-progress", progress(None));
+        let check = api::run_selftest_check(&host, port, &name).await;
+        let _ = app.emit("backend-selftest-progress", progress(Some(check.status)));
+        checks.push(check);
+    }
+
+    let report = SelfTestReport::new(checks);
+    log::info!(
+        target: LOG_TARGET,
+        "Backend self-test {} ({} checks)",
+        if report.passed { "passed" } else { "did not pass" },
+        total
+    );
+    Ok(report)
+}
+
+/// Save a backend file to `destination` without passing it through the webview, reporting
+/// `download-progress` events along the way. Returns the number of bytes written.
+#[tauri::command]
+async fn download_backend_file(
+    app: AppHandle,
+    download_id: String,
+    path: String,
+    destination: String,
+) -> Result<u64, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let cancel = CancellationToken::new();
+    {
+        let mut downloads = state.downloads.lock_recover();
+        if downloads.contains_key(&download_id) {
+            let message = format!("download '{}' is already running", download_id);
+            return Err(BackendApiError::InvalidRequest(message));
+        }
+        downloads.insert(download_id.clone(), cancel.clone());
+    }
+
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let destination = std::path::PathBuf::from(destination);
+    let result = api::download(&host, port, &path, &destination, &cancel, |written, total| {
+        let _ = app.emit("download-progress", DownloadProgressPayload {
+            download_id: download_id.clone(),
+            written,
+            total,
+        });
+    })
+    .await;
+    state.downloads.lock_recover().remove(&download_id);
+
+    match &result {
+        Ok(written) => log::info!(
+            target: LOG_TARGET,
+            "Downloaded {} ({} bytes) to {}",
+            path,
+            written,
+            destination.display()
+        ),
+        Err(e) => log::warn!(target: LOG_TARGET, "Download of {} failed: {}", path, e),
+    }
+    result
+}
+
+/// Cancel a running download, returns false if no download has that id
+#[tauri::command]
+fn cancel_download(state: tauri::State<'_, BackendState>, download_id: String) -> bool {
+    match state.downloads.lock_recover().get(&download_id) {
+        Some(cancel) => {
+            cancel.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Files the backend produced for a session, for a downloads panel to fetch with
+/// `download_backend_file`. Cached per session, `refresh` asks the backend again for
+/// artifacts written since.
+#[tauri::command]
+async fn list_artifacts(
+    app: AppHandle,
+    session_id: String,
+    refresh: Option<bool>,
+) -> Result<Vec<Artifact>, BackendApiError> {
+    let state = app.state::<BackendState>();
+    if !refresh.unwrap_or(false) {
+        if let Some(artifacts) = state.artifacts.lock_recover().get(&session_id) {
+            return Ok(artifacts.clone());
+        }
+    }
+
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    let artifacts = api::fetch_artifacts(&host, port, &session_id).await?;
+    state.artifacts.lock_recover().insert(session_id, artifacts.clone());
+    Ok(artifacts)
+}
+
+/// Fetch the current QBER and key rates of a backend session
+#[tauri::command]
+async fn get_session_metrics(app: AppHandle, session_id: String) -> Result<SessionMetrics, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    api::fetch_session_metrics(&host, port, &session_id).await
+}
+
+/// Poll a session's metrics every `interval_ms` and emit them as `session-metrics` events until
+/// the session completes or `stop_session_metrics` is called
+#[tauri::command]
+fn watch_session_metrics(
+    app: AppHandle,
+    session_id: String,
+    interval_ms: Option<u64>,
+) -> Result<(), BackendApiError> {
+    let interval = Duration::from_millis(
+        interval_ms
+            .unwrap_or(DEFAULT_METRICS_INTERVAL_MS)
+            .max(MIN_METRICS_INTERVAL_MS),
+    );
+    let state = app.state::<BackendState>();
+    let cancel = CancellationToken::new();
+    {
+        let mut watches = state.metric_watches.lock_recover();
+        if watches.contains_key(&session_id) {
+            let message = format!("session '{}' is already being watched", session_id);
+            return Err(BackendApiError::InvalidRequest(message));
+        }
+        watches.insert(session_id.clone(), cancel.clone());
+    }
+    tauri::async_runtime::spawn(poll_session_metrics(app.clone(), session_id, interval, cancel));
+    Ok(())
+}
+
+/// Stop the poller started by `watch_session_metrics`, returns false if there was none
+#[tauri::command]
+fn stop_session_metrics(state: tauri::State<'_, BackendState>, session_id: String) -> bool {
+    match state.metric_watches.lock_recover().remove(&session_id) {
+        Some(cancel) => {
+            cancel.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+async fn poll_session_metrics(
+    app: AppHandle,
+    session_id: String,
+    interval: Duration,
+    cancel: CancellationToken,
+) {
+    let state = app.state::<BackendState>();
+    loop {
+        // Read the address every time, a restart may have moved the backend to another port
+        let host = state.config().host;
+        let port = *state.port.lock_recover();
+        let result = tokio::select! {
+            _ = cancel.cancelled() => break,
+            result = api::fetch_session_metrics(&host, port, &session_id) => result,
+        };
+        let (metrics, finished, error) = match result {
+            Ok(metrics) => {
+                let finished = metrics.state != SessionState::Active;
+                (Some(metrics), finished, None)
+            }
+            // The backend has forgotten the session, so it is over
+            Err(e @ (BackendApiError::NotSupported(_) | BackendApiError::InvalidRequest(_))) => {
+                (None, true, Some(e.to_string()))
+            }
+            // The backend may be restarting, keep trying without bothering the UI
+            Err(e) => {
+                log::debug!(target: LOG_TARGET, "Metrics of session {} unavailable: {}", session_id, e);
+                (None, false, None)
+            }
+        };
+        if metrics.is_some() || finished {
+            let _ = app.emit("session-metrics", SessionMetricsPayload {
+                session_id: session_id.clone(),
+                metrics,
+                finished,
+                error,
+            });
+        }
+        if finished {
+            log::info!(target: LOG_TARGET, "Session {} finished, stopped polling its metrics", session_id);
+            // A list cached while it ran would miss the files written at the end
+            state.artifacts.lock_recover().remove(&session_id);
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    // A cancelled watch was already removed, and may have been replaced by a new one since
+    if !cancel.is_cancelled() {
+        state.metric_watches.lock_recover().remove(&session_id);
+    }
+}
+
+/// Change the backend's log level, live if it supports `/loglevel`, otherwise by restarting
+/// it with `QKD_LOG_LEVEL`. The level is kept in the config so later restarts keep it.
+#[tauri::command]
+async fn set_backend_log_level(app: AppHandle, level: String) -> Result<String, BackendApiError> {
+    let level = level.to_ascii_uppercase();
+    if !api::LOG_LEVELS.contains(&level.as_str()) {
+        return Err(BackendApiError::InvalidRequest(format!(
+            "log level must be one of {}",
+            api::LOG_LEVELS.join(", ")
+        )));
+    }
+
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    match api::set_log_level(&host, port, &level).await {
+        Ok(effective) => {
+            log::info!(target: LOG_TARGET, "Backend log level set to {}", effective);
+            // The backend already runs at the new level, a config that can't be saved only
+            // means the next launch won't
+            if let Err(e) = remember_log_level(&app, &level) {
+                log::warn!(target: LOG_TARGET, "Failed to keep log level {}: {}", level, e);
+            }
+            Ok(effective)
+        }
+        Err(BackendApiError::NotSupported(_)) if state.config().mode == BackendMode::Embedded => {
+            log::info!(target: LOG_TARGET, "Backend can't change log level live, restarting it at {}", level);
+            remember_log_level(&app, &level).map_err(BackendApiError::Io)?;
+            restart_backend(app.clone(), None)
+                .await
+                .map_err(BackendApiError::Unreachable)?;
+            Ok(level)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Keep `level` as `QKD_LOG_LEVEL` in the config, persisted like `set_backend_config` does
+fn remember_log_level(app: &AppHandle, level: &str) -> Result<(), String> {
+    let mut config = app.state::<BackendState>().config();
+    config.env.insert("QKD_LOG_LEVEL".to_string(), level.to_string());
+    store_config(app, config)
+}
+
+/// Cancel a running backend job. If the backend can't cancel jobs and `allow_restart` is set,
+/// fall back to restarting it, which aborts all work in progress and takes a few seconds.
+/// The restart is user-initiated, so it doesn't count against the crash restart limit.
+#[tauri::command]
+async fn cancel_backend_job(
+    app: AppHandle,
+    job_id: String,
+    allow_restart: Option<bool>,
+) -> Result<CancelMethod, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+    match api::cancel_job(&host, port, &job_id).await {
+        Ok(()) => Ok(CancelMethod::Soft),
+        Err(BackendApiError::NotSupported(_))
+            if allow_restart.unwrap_or(false) && state.config().mode == BackendMode::Embedded =>
+        {
+            log::info!(target: LOG_TARGET, "Backend can't cancel job {}, restarting it instead", job_id);
+            restart_backend(app.clone(), None)
+                .await
+                .map_err(BackendApiError::Unreachable)?;
+            Ok(CancelMethod::Restart)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Warm up the backend's QKD engines with a minimal simulation, returning how long it took.
+/// Only the first call per backend launch does any work, later calls return the recorded time.
+#[tauri::command]
+async fn warmup_backend(app: AppHandle) -> Result<u64, BackendApiError> {
+    let state = app.state::<BackendState>();
+    let _guard = state.warmup.lock().await;
+    if let Some(took) = state.startup.lock_recover().warmup {
+        return Ok(took.as_millis() as u64);
+    }
+    let host = state.config().host;
+    let port = *state.port.lock_recover();
+
+    let started = Instant::now();
+    api::warmup(&host, port).await?;
+    let took = started.elapsed();
+
+    state.startup.lock_recover().record_warmup(took);
+    let duration_ms = took.as_millis() as u64;
+    log::info!(target: LOG_TARGET, "Backend warmed up in {}ms", duration_ms);
+    let _ = app.emit("backend-warm", BackendWarmPayload { duration_ms });
+    Ok(duration_ms)
+}
+
+/// Follow-up work once the backend is ready: log its version and warm it up if configured
+async fn on_backend_ready(app: &AppHandle, host: &str, port: u16) {
+    if !run_preflight(app, host, port).await {
+        return;
+    }
+    log_backend_version(app, host, port).await;
+    if app.state::<BackendState>().config().warmup_on_ready {
+        if let Err(e) = warmup_backend(app.clone()).await {
+            log::warn!(target: LOG_TARGET, "Backend warmup failed: {}", e);
+        }
+    }
+}
 
-// Store the backend process handle so we can kill it on shutdown
-struct BackendState {
-    child: Option<tauri_plugin_shell::process::CommandChild>,
-    ready: Arc<Mutex<bool>>,
+/// Ask the backend for missing prerequisites and fail the launch with its advice if one is
+/// critical, rather than leaving the UI on a backend that can't work. Backends without
+/// `/preflight` skip this. Returns false if preflight failed.
+async fn run_preflight(app: &AppHandle, host: &str, port: u16) -> bool {
+    let report = match api::fetch_preflight(host, port).await {
+        Ok(report) => report,
+        Err(BackendApiError::NotSupported(_)) => return true,
+        Err(e) => {
+            log::warn!(target: LOG_TARGET, "Could not run backend preflight: {}", e);
+            return true;
+        }
+    };
+    for check in report.checks.iter().filter(|check| check.status != PreflightStatus::Ok) {
+        log::warn!(target: LOG_TARGET, "Preflight {}: {}", check.name, check.message);
+    }
+    let failure = report.failure();
+    let state = app.state::<BackendState>();
+    *state.preflight.lock_recover() = Some(report);
+    match failure {
+        Some(message) => {
+            log::error!(target: LOG_TARGET, "{}", message);
+            state.fail(app, message);
+            false
+        }
+        None => true,
+    }
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .manage(BackendState {
-            child: None,
-            ready: Arc::new(Mutex::new(false)),
-        })
-        .setup(|app| {
-            let ready_flag = app
-                .state::<BackendState>()
-                .ready
-                .clone();
-
-            // Start the backend sidecar
-            let shell = app.shell();
-            let sidecar = shell.sidecar("qkd-backend")
-                .expect("Failed to create sidecar command");
-            
-            let (mut rx, child) = sidecar.spawn()
-                .expect("Failed to spawn backend sidecar");
-            
-            // Store the child process handle
-            let state = app.state::<BackendState>();
-            {
-                let mut guard = state as *const BackendState as *mut BackendState;
-                unsafe {
-                    (*guard).child = Some(child);
-                }
-            }
-            
-            // Log backend output and monitor for startup in a separate thread
-            tauri::async_runtime::spawn(async move {
-                use tauri_plugin_shell::process::CommandEvent;
-                let mut started = false;
-                while let Some(event) = rx.recv().await {
-                    match event {
-                        CommandEvent::Stdout(line) => {
-                            let output = String::from_utf8_lossy(&line);
-                            println!("[Backend] {}", output);
-                            
-                            // Check if backend is ready
-                            if output.contains("Uvicorn running on") || 
-                               output.contains("Listening on") ||
-                               output.contains("API Docs") {
-                                started = true;
-                                *ready_flag.lock().unwrap() = true;
-                                println!("✓ Backend is ready for connections");
-                            }
+/// What the backend's preflight check reported for the current launch, `None` before the
+/// backend is ready or when it doesn't offer one
+#[tauri::command]
+fn get_preflight(state: tauri::State<'_, BackendState>) -> Option<PreflightReport> {
+    state.preflight.lock_recover().clone()
+}
+
+/// Fetch and log the backend version once it is ready
+async fn log_backend_version(app: &AppHandle, host: &str, port: u16) {
+    match api::fetch_version(host, port).await {
+        Ok(version) => {
+            log::info!(
+                target: LOG_TARGET,
+                "Backend version {} ({})",
+                version.version,
+                version.git_sha.as_deref().unwrap_or("unknown build")
+            );
+            *app.state::<BackendState>().version.lock_recover() = Some(version);
+        }
+        Err(e) => log::warn!(target: LOG_TARGET, "Could not determine backend version: {}", e),
+    }
+}
+
+/// Pause or resume `backend-log` events, e.g. for a "pause console" button. Output keeps going
+/// to the log buffer and file while paused, so `get_backend_logs` still has the full history.
+#[tauri::command]
+fn set_log_forwarding(state: tauri::State<'_, BackendState>, enabled: bool) {
+    if state.log_forwarding.swap(enabled, Ordering::SeqCst) != enabled {
+        log::info!(target: LOG_TARGET, "Backend log forwarding {}", if enabled { "resumed" } else { "paused" });
+    }
+}
+
+/// Return the buffered backend output, oldest line first
+#[tauri::command]
+fn get_backend_logs(state: tauri::State<'_, BackendState>) -> Vec<LogLine> {
+    state.logs.lock_recover().snapshot()
+}
+
+/// Empty the output buffer for a clean slate before reproducing an issue. Saved crash logs are
+/// deleted too when `crash_logs` is set, which also needs `confirm` since that can't be undone.
+#[tauri::command]
+fn clear_backend_logs(app: AppHandle, crash_logs: bool, confirm: bool) -> Result<LogsCleared, String> {
+    if crash_logs && !confirm {
+        return Err("Deleting crash logs must be confirmed".to_string());
+    }
+    let crash_logs = if crash_logs {
+        let dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+        logs::delete_crash_logs(&dir).map_err(|e| format!("Failed to delete crash logs: {}", e))?
+    } else {
+        0
+    };
+    // Lines still in flight from the forwarder land after this, like any new output
+    let lines = app.state::<BackendState>().logs.lock_recover().clear();
+    log::info!(target: LOG_TARGET, "Cleared {} buffered lines and {} crash logs", lines, crash_logs);
+    Ok(LogsCleared { lines, crash_logs })
+}
+
+/// Return the most recent error lines from the buffered output, newest first
+#[tauri::command]
+fn get_backend_errors(state: tauri::State<'_, BackendState>, limit: Option<usize>) -> Vec<LogLine> {
+    let limit = limit.unwrap_or(MAX_ERROR_LINES).min(MAX_ERROR_LINES);
+    state.logs.lock_recover().recent_errors(limit)
+}
+
+/// Return buffered lines containing `pattern`, where `*` and `?` are wildcards, newest first.
+/// With `level`, only lines at that severity or above are returned.
+#[tauri::command]
+fn get_backend_logs_filtered(
+    state: tauri::State<'_, BackendState>,
+    pattern: String,
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+) -> Vec<LogLine> {
+    let limit = limit.unwrap_or(MAX_ERROR_LINES).min(MAX_ERROR_LINES);
+    state.logs.lock_recover().matching(&LineFilter::new(&pattern, level), limit)
+}
+
+/// Spawn the backend sidecar, store its handle and start the monitor and health tasks.
+/// In remote mode only the health checks are started against the configured host.
+async fn spawn_backend(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    let config = state.config();
+    config
+        .validate()
+        .inspect_err(|e| state.fail(app, e.as_str()))?;
+    if config.mode == BackendMode::Remote {
+        log::info!(
+            target: LOG_TARGET,
+            "Embedded backend disabled; expecting external backend on {}:{}",
+            config.host, config.port
+        );
+        *state.port.lock_recover() = config.port;
+        *state.instance_id.lock_recover() = None;
+        *state.resources.lock_recover() = ResourceHistory::default();
+        *state.version.lock_recover() = None;
+        *state.protocols.lock_recover() = None;
+        *state.preflight.lock_recover() = None;
+        state.artifacts.lock_recover().clear();
+        *state.startup.lock_recover() = StartupMetrics::begin(Instant::now());
+        // There is no output to watch for a remote backend, only HTTP can tell it is ready
+        *state.readiness.lock_recover() = ReadinessSignals { log: true, http: false };
+        state.set_phase(app, BackendPhase::WaitingForReady);
+        state.generation.fetch_add(1, Ordering::SeqCst);
+
+        let cancel = state.renew_tasks();
+        // No local process to die, the loop runs until ready or its deadline
+        let health = spawn_health_task(app, &config, cancel.clone(), ProcessAlive::new());
+        let mut tasks = state.tasks.lock_recover();
+        tasks.push(health);
+        if config.metrics_event.enabled {
+            tasks.push(spawn_metrics_event_task(app, &config, cancel.clone()));
+        }
+        if config.watchdog.enabled {
+            tasks.push(spawn_watchdog_task(app, &config, cancel));
+        }
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    return spawn_embedded(app, config).await.inspect_err(|e| state.fail(app, e.as_str()));
+    // validate() already rejects embedded mode here, this is just for completeness
+    #[cfg(mobile)]
+    Err("The embedded backend is not available on mobile".to_string())
+}
+
+/// Embedded mode: run the sidecar and supervise it
+#[cfg(desktop)]
+async fn spawn_embedded(app: &AppHandle, config: BackendConfig) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    log::info!(target: LOG_TARGET, "Backend mode: embedded sidecar '{}'", config.sidecar);
+    let sidecar_path = check_sidecar(&config.sidecar)?;
+    let backend_arch = arch::binary_arch(&sidecar_path);
+    log::info!(
+        target: LOG_TARGET,
+        "Backend binary: {} ({})",
+        sidecar_path.display(),
+        backend_arch.unwrap_or("unknown architecture")
+    );
+    *state.backend_arch.lock_recover() = backend_arch;
+    if let Some(warning) = arch::rosetta_warning(backend_arch, &arch::host_arch()) {
+        log::warn!(target: LOG_TARGET, "{}", warning);
+    }
+    let port = find_free_port(&config.host, config.port)?;
+    if port != config.port {
+        log::warn!(target: LOG_TARGET, "Port {} is in use, starting backend on port {}", config.port, port);
+        let _ = app.emit("backend-port-changed", BackendPortChangedPayload {
+            requested: config.port,
+            port,
+        });
+    }
+    *state.port.lock_recover() = port;
+    *state.version.lock_recover() = None;
+    *state.protocols.lock_recover() = None;
+    *state.preflight.lock_recover() = None;
+    state.artifacts.lock_recover().clear();
+
+    let redactor = Redactor::new(&config.redaction)?;
+    if let Some(command) = config.pre_start_command.clone() {
+        state.set_phase(app, BackendPhase::PreStart);
+        run_pre_start_hook(app, &config, command).await?;
+        if state.shutting_down.load(Ordering::SeqCst) {
+            log::info!(target: LOG_TARGET, "Backend was stopped while the pre-start command ran, not starting it");
+            return Ok(());
+        }
+    }
+    state.set_phase(app, BackendPhase::Spawning);
+    let mut repeats = RepeatFilter::new(config.log_repeat);
+    // Unique per launch, so a backend left over from an earlier run can't pass as this one
+    *state.instance_id.lock_recover() = Some(format!("{}-{:08x}", std::process::id(), generate_seed()));
+    let (mut rx, child) = spawn_sidecar(app, &config, port).await?;
+
+    // Store the child process handle
+    let pid = child.pid();
+    log::info!(target: LOG_TARGET, "Backend PID: {}", pid);
+    *state.child.lock_recover() = Some(child);
+    *state.pid.lock_recover() = Some(pid);
+    *state.resources.lock_recover() = ResourceHistory::new(config.resources.history);
+    *state.started_at.lock_recover() = Some(Instant::now());
+    *state.startup.lock_recover() = StartupMetrics::begin(Instant::now());
+    *state.readiness.lock_recover() = ReadinessSignals::default();
+    state.generation.fetch_add(1, Ordering::SeqCst);
+    state.set_phase(app, BackendPhase::WaitingForReady);
+
+    // Stream output lines to the frontend in batches
+    let (mut queue, output_rx) = OutputQueue::new();
+    let forwarder = tauri::async_runtime::spawn(logs::forward_logs(
+        app.clone(),
+        output_rx,
+        config.log_batch_interval,
+    ));
+
+    // Fresh cancellation token for the tasks tied to this child
+    let cancel = state.renew_tasks();
+
+    let (exit_tx, exit_rx) = std::sync::mpsc::channel();
+    *state.exit_rx.lock_recover() = Some(exit_rx);
+
+    // Log backend output and monitor for startup in a separate thread
+    let monitor_app = app.clone();
+    let monitor_cancel = cancel.clone();
+    let alive = ProcessAlive::new();
+    let monitor_alive = alive.clone();
+    let ready_markers = config.ready_markers.clone();
+    let monitor = tauri::async_runtime::spawn(async move {
+        let mut started = false;
+        let mut early = EarlyStderr::new(STARTUP_STDERR_LINES);
+        loop {
+            let event = tokio::select! {
+                _ = monitor_cancel.cancelled() => break,
+                event = rx.recv() => event,
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let output = String::from_utf8_lossy(&line);
+                    push_log(&monitor_app, &mut queue, &redactor, &mut repeats, LogStream::Stdout, &output);
+                    monitor_app
+                        .state::<BackendState>()
+                        .startup
+                        .lock_recover()
+                        .record_first_stdout(Instant::now());
+
+                    // Check if backend is ready, HTTP health stays the fallback
+                    if let Some(marker) = readiness::parse_ready_marker(&output, &ready_markers) {
+                        started = true;
+                        let actual_port = marker.port.unwrap_or(port);
+                        if actual_port != port {
+                            reconcile_port(&monitor_app, port, actual_port);
                         }
-                        CommandEvent::Stderr(line) => {
-                            eprintln!("[Backend Error] {}", String::from_utf8_lossy(&line));
+                        if signal_backend_ready(&monitor_app, actual_port, "log") {
+                            log::info!(target: LOG_TARGET, "Backend is ready for connections (saw '{}')", marker.marker);
+                        }
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    let output = String::from_utf8_lossy(&line);
+                    push_log(&monitor_app, &mut queue, &redactor, &mut repeats, LogStream::Stderr, &output);
+                    if !early.is_full() {
+                        early.push(&redactor.redact(&output));
+                    }
+                }
+                CommandEvent::Terminated(payload) => {
+                    monitor_alive.mark_dead();
+                    // Make sure a collapsed run shows up before the exit report reads the buffer
+                    if let Some(summary) = repeats.flush() {
+                        keep_log(&monitor_app, &mut queue, summary);
+                    }
+                    let kind = ExitKind::classify(payload.code, payload.signal);
+                    log::info!(
+                        target: LOG_TARGET,
+                        "Backend process terminated with code {:?}, signal {:?}: {}",
+                        payload.code,
+                        payload.signal,
+                        kind.describe()
+                    );
+                    let state = monitor_app.state::<BackendState>();
+                    state.take_child();
+                    let exit = match payload.code {
+                        Some(code) => format!("{} (exit code {})", kind.describe(), code),
+                        None => kind.describe().to_string(),
+                    };
+                    let recent_stderr = state.logs.lock_recover().recent_stderr(EXIT_STDERR_LINES);
+                    let exited = BackendExitedPayload {
+                        timestamp: unix_millis(),
+                        kind,
+                        reason: kind.describe(),
+                        exit_code: payload.code,
+                        signal: payload.signal,
+                        intentional: state.shutting_down.load(Ordering::SeqCst),
+                        recent_stderr,
+                    };
+                    {
+                        let mut exits = state.exits.lock_recover();
+                        if exits.len() == EXIT_HISTORY {
+                            exits.pop_front();
                         }
-                        CommandEvent::Terminated(payload) => {
-                            println!("[Backend] Process terminated with code: {:?}", payload.code);
-                            break;
+                        exits.push_back(exited.clone());
+                    }
+                    // A backend dying before it became ready says why in its first stderr lines,
+                    // which by now may have scrolled out of `recent_stderr`
+                    let early_exit = !exited.intentional && !state.is_ready();
+                    if !exited.intentional {
+                        save_crash_log(&monitor_app, exited.timestamp);
+                        if early_exit {
+                            log::error!(target: LOG_TARGET, "{}", early.describe_exit(&exit));
+                            state.record_error(early.describe_exit(&exit));
+                        } else if payload.code != Some(0) {
+                            state.record_error(exit.clone());
                         }
-                        _ => {}
                     }
+                    let _ = monitor_app.emit("backend-exited", exited);
+                    let _ = exit_tx.send(());
+                    if payload.code != Some(0) {
+                        let stderr = if early_exit { early.lines().to_vec() } else { Vec::new() };
+                        schedule_restart(&monitor_app, payload.code, stderr);
+                    } else {
+                        state.set_phase(&monitor_app, BackendPhase::Stopped);
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let stopping = monitor_app.state::<BackendState>().shutting_down.load(Ordering::SeqCst);
+        if !started && !stopping && !monitor_cancel.is_cancelled() {
+            log::warn!(target: LOG_TARGET, "Backend process exited without clear startup confirmation");
+        }
+    });
+
+    // Spawn a separate task to wait for backend health check
+    let health = spawn_health_task(app, &config, cancel.clone(), alive);
+
+    let mut tasks = state.tasks.lock_recover();
+    tasks.push(forwarder);
+    tasks.push(monitor);
+    tasks.push(health);
+    if config.metrics_event.enabled {
+        tasks.push(spawn_metrics_event_task(app, &config, cancel.clone()));
+    }
+    if config.resources.enabled {
+        tasks.push(spawn_resource_task(app, &config, pid, cancel.clone()));
+    }
+    if config.watchdog.enabled {
+        tasks.push(spawn_watchdog_task(app, &config, cancel));
+    }
+    Ok(())
+}
+
+/// Start the health-check loop for the backend at `BackendState::target`
+fn spawn_health_task(
+    app: &AppHandle,
+    config: &BackendConfig,
+    cancel: CancellationToken,
+    alive: ProcessAlive,
+) -> tauri::async_runtime::JoinHandle<()> {
+    let health_app = app.clone();
+    let health_config = app.state::<BackendState>().health_config(config);
+    tauri::async_runtime::spawn(async move {
+        wait_for_backend_health(health_app, health_config, cancel, alive).await;
+    })
+}
+
+fn spawn_watchdog_task(
+    app: &AppHandle,
+    config: &BackendConfig,
+    cancel: CancellationToken,
+) -> tauri::async_runtime::JoinHandle<()> {
+    let watchdog_app = app.clone();
+    let watchdog = config.watchdog.clone();
+    let health = app.state::<BackendState>().health_config(config);
+    let embedded = config.mode == BackendMode::Embedded;
+    tauri::async_runtime::spawn(async move {
+        watch_backend(watchdog_app, embedded, watchdog, health, cancel).await;
+    })
+}
+
+/// Emit `backend-metrics` at the configured interval until the backend is stopped or respawned
+fn spawn_metrics_event_task(
+    app: &AppHandle,
+    config: &BackendConfig,
+    cancel: CancellationToken,
+) -> tauri::async_runtime::JoinHandle<()> {
+    let metrics_app = app.clone();
+    let interval = config.metrics_event.interval;
+    tauri::async_runtime::spawn(async move {
+        metrics::run_every(interval, &cancel, || emit_backend_metrics(metrics_app.clone())).await;
+    })
+}
+
+/// One `backend-metrics` event, skipped while the backend is still starting
+async fn emit_backend_metrics(app: AppHandle) {
+    let state = app.state::<BackendState>();
+    let phase = state.phase();
+    if matches!(phase, BackendPhase::PreStart | BackendPhase::Spawning | BackendPhase::WaitingForReady) {
+        return;
+    }
+    // The ping goes through the circuit breaker, so a backend known to be down costs no timeout
+    let (host, port) = state.target();
+    let latency_ms = if state.is_ready() {
+        api::ping(&host, port).await.ok().map(|ping| ping.latency_ms)
+    } else {
+        None
+    };
+    let status = backend_status(&state);
+    let _ = app.emit("backend-metrics", BackendMetricsPayload {
+        state: phase,
+        latency_ms,
+        circuit: status.circuit,
+        uptime_secs: status.uptime_secs,
+        restart_count: status.restart_count,
+        last_error: status.last_error,
+    });
+}
+
+/// Sample the backend process's memory and CPU use into `BackendState::resources` until it exits,
+/// warning once each time its RSS climbs over the configured threshold
+#[cfg(desktop)]
+fn spawn_resource_task(
+    app: &AppHandle,
+    config: &BackendConfig,
+    pid: u32,
+    cancel: CancellationToken,
+) -> tauri::async_runtime::JoinHandle<()> {
+    let app = app.clone();
+    let interval = config.resources.interval;
+    let threshold = config.resources.rss_warning_bytes();
+    tauri::async_runtime::spawn(async move {
+        let mut sampler = ResourceSampler::new(pid);
+        let mut over_threshold = false;
+        // Cancelled by the sampler itself once the process is gone
+        let stop = cancel.child_token();
+        metrics::run_every(interval, &stop, || {
+            let state = app.state::<BackendState>();
+            match sampler.sample(unix_millis()) {
+                Some(sample) => {
+                    state.resources.lock_recover().push(sample);
+                    let over = threshold.is_some_and(|threshold| sample.rss_bytes > threshold);
+                    if over && !over_threshold {
+                        log::warn!(
+                            target: LOG_TARGET,
+                            "Backend is using {} MiB of memory",
+                            sample.rss_bytes / (1024 * 1024)
+                        );
+                        let _ = app.emit("backend-resource-warning", ResourceWarningPayload {
+                            pid,
+                            rss_bytes: sample.rss_bytes,
+                            threshold_bytes: threshold.unwrap_or_default(),
+                        });
+                    }
+                    over_threshold = over;
                 }
-                if !started {
-                    eprintln!("⚠ Backend process exited without clear startup confirmation");
+                None => {
+                    log::debug!(target: LOG_TARGET, "Backend process {} is gone, resource sampling stopped", pid);
+                    stop.cancel();
                 }
-            });
+            }
+            std::future::ready(())
+        })
+        .await;
+    })
+}
+
+/// Periodically check a ready backend and flag it when it stops answering while still running.
+/// Exits and startup are handled elsewhere, so checks are skipped until the backend is ready.
+async fn watch_backend(
+    app: AppHandle,
+    embedded: bool,
+    watchdog: WatchdogConfig,
+    health: HealthCheckConfig,
+    cancel: CancellationToken,
+) {
+    let state = app.state::<BackendState>();
+    let mut failures = 0;
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tokio::time::sleep(watchdog.interval) => {}
+        }
 
-            // Spawn a separate task to wait for backend health check
-            let ready_flag_clone = app.state::<BackendState>().ready.clone();
+        let ready = state.is_ready();
+        let alive = !embedded || state.pid.lock_recover().is_some();
+        if !ready || !alive || state.shutting_down.load(Ordering::SeqCst) {
+            failures = 0;
+            continue;
+        }
+
+        let (host, port) = state.target();
+        let probe = tokio::select! {
+            _ = cancel.cancelled() => return,
+            probe = perform_health_check(&health, &host, port) => probe,
+        };
+        let error = match probe {
+            Ok(HealthOk { ready: true, .. }) => {
+                if state.transition(&app, |phase| phase == BackendPhase::Unresponsive, BackendPhase::Ready) {
+                    log::info!(target: LOG_TARGET, "Backend is responding again");
+                    *state.last_error.lock_recover() = None;
+                }
+                failures = 0;
+                // A healthy backend closes the API circuit without waiting for a user request
+                api::reset_breaker();
+                continue;
+            }
+            Ok(HealthOk { ready: false, .. }) => "Backend reported not ready".to_string(),
+            Err(HealthError::ConnectionRefused) => format!("Nothing listening on {}:{}", host, port),
+            Err(e) => e.to_string(),
+        };
+
+        failures += 1;
+        log::warn!(target: LOG_TARGET, "Backend health check failed ({}/{}): {}", failures, watchdog.max_failures, error);
+        if failures < watchdog.max_failures.max(1) {
+            continue;
+        }
+
+        state.set_phase(&app, BackendPhase::Unresponsive);
+        state.record_error(format!("Backend stopped answering health checks: {}", error));
+        let restarting = watchdog.auto_restart && embedded;
+        log::error!(target: LOG_TARGET, "Backend is running but unresponsive");
+        let _ = app.emit("backend-unresponsive", BackendUnresponsivePayload {
+            failures,
+            last_error: error,
+            restarting,
+        });
+        if restarting {
+            // restart_backend aborts this task, so run it on its own
+            let restart_app = app.clone();
             tauri::async_runtime::spawn(async move {
-                wait_for_backend_health(ready_flag_clone).await;
+                if let Err(e) = restart_backend(restart_app, None).await {
+                    log::error!(target: LOG_TARGET, "Failed to restart unresponsive backend: {}", e);
+                }
             });
+            return;
+        }
+        failures = 0;
+    }
+}
+
+/// Resolve the sidecar the way the shell plugin does (next to the app executable) and check
+/// it can be run, so a broken install gets a clear message instead of a retried spawn error
+#[cfg(desktop)]
+fn check_sidecar(name: &str) -> Result<std::path::PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the app executable: {}", e))?;
+    let mut path = exe.parent().unwrap_or(&exe).join(name);
+    if cfg!(windows) {
+        path.as_mut_os_string().push(".exe");
+    }
+
+    let missing = || format!("Backend binary not found at {}; reinstall the app", path.display());
+    let metadata = std::fs::metadata(&path).map_err(|_| missing())?;
+    if !metadata.is_file() {
+        return Err(missing());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(format!(
+                "Backend binary at {} is not executable; reinstall the app",
+                path.display()
+            ));
+        }
+    }
+    Ok(path)
+}
+
+/// Create and spawn the sidecar process, retrying transient failures
+/// (e.g. antivirus briefly locking the binary) with a short backoff
+#[cfg(desktop)]
+async fn spawn_sidecar(
+    app: &AppHandle,
+    config: &BackendConfig,
+    port: u16,
+) -> Result<(Receiver<CommandEvent>, CommandChild), String> {
+    let attempts = config.spawn_attempts.max(1);
+    let seed = app.state::<BackendState>().seed.lock_recover().to_string();
+    let instance_id = app.state::<BackendState>().instance_id.lock_recover().clone().unwrap_or_default();
+    // The backend binds the socket instead of the TCP port when this is set
+    let socket_env: Vec<(&str, String)> = match &config.socket_path {
+        Some(path) if cfg!(unix) => vec![("QKD_UDS", path.display().to_string())],
+        _ => Vec::new(),
+    };
+    let working_dir = sidecar_working_dir(app, config);
+    let mut delay = Duration::from_millis(SPAWN_RETRY_DELAY_MS);
+    let mut last_error = String::new();
 
-            println!("🔬 QKD-Lab Backend Startup Initiated");
+    for attempt in 1..=attempts {
+        // The bundled entry point reads QKD_PORT, `--port` covers run_server.py
+        let result = app
+            .shell()
+            .sidecar(&config.sidecar)
+            .map_err(|e| format!("Failed to create sidecar command: {}", e))
+            .map(|sidecar| match &working_dir {
+                Some(dir) => sidecar.current_dir(dir),
+                None => sidecar,
+            })
+            .and_then(|sidecar| {
+                sidecar
+                    .args(["--port", &port.to_string(), "--seed", &seed])
+                    .args(&config.args)
+                    .envs(config.env.clone())
+                    .env("QKD_HOST", &config.host)
+                    .env("QKD_PORT", port.to_string())
+                    .env("QKD_SEED", &seed)
+                    .env("QKD_INSTANCE_ID", &instance_id)
+                    .envs(socket_env.clone())
+                    .spawn()
+                    .map_err(|e| format!("Failed to spawn backend sidecar: {}", e))
+            });
 
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+        match result {
+            Ok(spawned) => return Ok(spawned),
+            // Not transient, retrying won't change the binary
+            Err(e) if arch::is_exec_format_error(&e) => {
+                log::error!(target: LOG_TARGET, "{}", e);
+                let backend_arch = *app.state::<BackendState>().backend_arch.lock_recover();
+                return Err(arch::mismatch_message(backend_arch, &arch::host_arch()));
             }
-            Ok(())
-        })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::Destroyed = event {
-                // Kill the backend when the window is destroyed
-                let state = window.state::<BackendState>();
-                let mut guard = state as *const BackendState as *mut BackendState;
-                unsafe {
-                    if let Some(child) = (*guard).child.take() {
-                        let _ = child.kill();
-                        println!("Backend process terminated");
-                    }
+            Err(e) => {
+                log::warn!(target: LOG_TARGET, "{} (attempt {}/{})", e, attempt, attempts);
+                last_error = e;
+            }
+        }
+
+        if attempt < attempts {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Run the configured pre-start command ahead of a spawn, keeping its output in the log buffer
+/// so a failing hook can be diagnosed like the backend itself. The hook blocks for up to
+/// `PRE_START_TIMEOUT`, so it runs on the blocking pool rather than the caller's thread.
+#[cfg(desktop)]
+async fn run_pre_start_hook(app: &AppHandle, config: &BackendConfig, command: String) -> Result<(), String> {
+    log::info!(target: LOG_TARGET, "Running pre-start command: {}", command);
+    let working_dir = sidecar_working_dir(app, config);
+    let env = config.env.clone();
+    let run = tauri::async_runtime::spawn_blocking(move || {
+        hook::run_pre_start(&command, working_dir.as_deref(), &env, PRE_START_TIMEOUT)
+    })
+    .await
+    .map_err(|e| format!("Pre-start command did not complete: {}", e))?;
+    let state = app.state::<BackendState>();
+    let mut logs = state.logs.lock_recover();
+    for line in run.output {
+        log::info!(target: BACKEND_OUTPUT_TARGET, "{}", line.line);
+        logs.push(line);
+    }
+    run.result
+}
+
+/// Directory the sidecar runs in: the configured one, otherwise the app data directory
+/// (created if needed). `None` leaves it in the app's own working directory.
+#[cfg(desktop)]
+fn sidecar_working_dir(app: &AppHandle, config: &BackendConfig) -> Option<std::path::PathBuf> {
+    config.working_dir_or(|| {
+        let dir = app.path().app_data_dir().ok()?;
+        match std::fs::create_dir_all(&dir) {
+            Ok(()) => Some(dir),
+            Err(e) => {
+                log::warn!(target: LOG_TARGET, "Cannot create {}: {}", dir.display(), e);
+                None
+            }
+        }
+    })
+}
+
+/// Tell the user the backend could not be started, without taking the app down
+fn report_spawn_failure(app: &AppHandle, reason: String) {
+    log::error!(target: LOG_TARGET, "{}", reason);
+    app.dialog()
+        .message(format!(
+            "The QKD Lab backend could not be started.\n\n{}\n\nYou can retry from the app once the problem is resolved.",
+            reason
+        ))
+        .title("QKD Lab")
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+    let _ = app.emit("backend-failed", BackendFailedPayload {
+        restart_count: 0,
+        exit_code: None,
+        reason,
+        stderr: Vec::new(),
+    });
+}
+
+/// The backend says it is listening somewhere other than where we started it, believe it
+#[cfg(desktop)]
+fn reconcile_port(app: &AppHandle, expected: u16, reported: u16) {
+    log::warn!(target: LOG_TARGET, "Backend reported port {} but was started on {}", reported, expected);
+    let state = app.state::<BackendState>();
+    *state.port.lock_recover() = reported;
+    let _ = app.emit("backend-port-changed", BackendPortChangedPayload {
+        requested: expected,
+        port: reported,
+    });
+    if state.is_ready() {
+        emit_backend_url(app);
+    }
+}
+
+/// Find a free local port, starting at `preferred` and scanning upward
+#[cfg(desktop)]
+fn find_free_port(host: &str, preferred: u16) -> Result<u16, String> {
+    scan_ports(host, preferred, PORT_SCAN_RANGE)
+}
+
+/// The first of the `count` ports from `preferred` up that is free on `host`
+#[cfg(desktop)]
+fn scan_ports(host: &str, preferred: u16, count: u16) -> Result<u16, String> {
+    let last = preferred.saturating_add(count.saturating_sub(1));
+    (preferred..=last)
+        .find(|&port| port_is_free(host, port))
+        .ok_or_else(|| format!("No free port available for the backend in range {}-{}", preferred, last))
+}
+
+/// Whether `port` can be bound on every address `host` resolves to, so a name like
+/// `localhost` is checked on whichever family the backend ends up binding
+#[cfg(desktop)]
+fn port_is_free(host: &str, port: u16) -> bool {
+    use std::net::ToSocketAddrs;
+    match (host, port).to_socket_addrs() {
+        Ok(addrs) => {
+            let addrs: Vec<_> = addrs.collect();
+            !addrs.is_empty() && addrs.iter().all(|addr| std::net::TcpListener::bind(addr).is_ok())
+        }
+        Err(_) => false,
+    }
+}
+
+/// Mask a line of backend output, collapse it if it keeps repeating, and keep what is left
+#[cfg(desktop)]
+fn push_log(
+    app: &AppHandle,
+    queue: &mut OutputQueue,
+    redactor: &Redactor,
+    repeats: &mut RepeatFilter,
+    stream: LogStream,
+    line: &str,
+) {
+    let line = LogLine::new(stream, redactor.redact(line.trim_end()));
+    for line in repeats.push(line) {
+        keep_log(app, queue, line);
+    }
+}
+
+/// Append a line to the log buffer and queue it for the output worker. The buffer is filled
+/// right away so exit reports always see the latest stderr.
+#[cfg(desktop)]
+fn keep_log(app: &AppHandle, queue: &mut OutputQueue, line: LogLine) {
+    app.state::<BackendState>().logs.lock_recover().push(line.clone());
+    queue.push(line);
+}
+
+/// Intentionally stop the backend: silence the restart supervisor, terminate the
+/// child gracefully, wait for the monitor to see it exit and drop its tasks.
+/// Returns false if nothing was running.
+fn stop_backend(app: &AppHandle) -> bool {
+    let state = app.state::<BackendState>();
+    state.shutting_down.store(true, Ordering::SeqCst);
+    #[cfg(desktop)]
+    {
+        let Some(child) = state.take_child() else {
+            state.cancel.lock_recover().cancel();
+            // Nothing to stop, but a crashed or failed backend is still not coming back
+            state.set_phase(app, BackendPhase::Stopped);
+            return false;
+        };
+        let exit_rx = state.exit_rx.lock_recover().take();
+
+        let grace = state.config().shutdown_grace;
+        if terminate_gracefully(child, grace) {
+            log::info!(target: LOG_TARGET, "Backend process exited gracefully");
+        } else {
+            log::info!(target: LOG_TARGET, "Backend process killed after {:?} grace period", grace);
+        }
+
+        // The monitor is still running, so it reports the exit before its token is cancelled.
+        // Without the confirmation the process may still hold the port on a quick relaunch.
+        if let Some(exit_rx) = exit_rx {
+            match exit_rx.recv_timeout(EXIT_CONFIRM_TIMEOUT) {
+                Ok(()) => log::info!(target: LOG_TARGET, "Backend exit confirmed"),
+                Err(_) => log::warn!(
+                    target: LOG_TARGET,
+                    "Backend exit not confirmed within {:?}, it may still be running",
+                    EXIT_CONFIRM_TIMEOUT
+                ),
+            }
+        }
+    }
+
+    state.cancel.lock_recover().cancel();
+    for task in state.tasks.lock_recover().drain(..) {
+        task.abort();
+    }
+    state.set_phase(app, BackendPhase::Stopped);
+    // On mobile there is never a process of our own to stop
+    cfg!(desktop)
+}
+
+/// Ask the backend to exit and force-kill it if it outlives the grace period.
+/// Returns true when the process exited on its own.
+#[cfg(desktop)]
+fn terminate_gracefully(child: CommandChild, grace: Duration) -> bool {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let pid = Pid::from_raw(child.pid() as i32);
+        if kill(pid, Signal::SIGTERM).is_ok() {
+            // The shell plugin reaps the child as soon as it exits, so probing the PID is enough
+            let deadline = Instant::now() + grace;
+            while Instant::now() < deadline {
+                if kill(pid, None).is_err() {
+                    return true;
                 }
+                std::thread::sleep(Duration::from_millis(50));
             }
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace;
+
+    let _ = child.kill();
+    false
+}
+
+/// Respawn the backend after an unexpected exit, with backoff and a restart limit. `stderr` holds
+/// the first output of a backend that died during startup, reported with `backend-restarting`
+/// or, if no restart follows, `backend-failed`.
+#[cfg(desktop)]
+fn schedule_restart(app: &AppHandle, exit_code: Option<i32>, stderr: Vec<String>) {
+    let state = app.state::<BackendState>();
+    if state.shutting_down.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let forgiven = state.forgive_restarts(Instant::now());
+    if forgiven > 0 {
+        log::info!(target: LOG_TARGET, "Backend ran stably before exiting, resetting its {} earlier restarts", forgiven);
+    }
+    let policy = *state.restart_policy.lock_recover();
+    let claimed = policy.claim_attempt(&mut state.restart_count.lock_recover());
+    if claimed == Err(NoRestart::Disabled) {
+        log::warn!(target: LOG_TARGET, "Backend exited and automatic restart is disabled");
+        // The crash itself is the error worth showing, already recorded by the monitor
+        state.set_phase(app, BackendPhase::Failed);
+        let _ = app.emit("backend-failed", BackendFailedPayload {
+            restart_count: *state.restart_count.lock_recover(),
+            exit_code,
+            reason: "Automatic restart is disabled".into(),
+            stderr,
+        });
+        return;
+    }
+    let Ok(attempt) = claimed else {
+        log::error!(target: LOG_TARGET, "Backend crashed {} times, giving up on automatic restart", policy.max_restarts);
+        let mut message = format!("Backend crashed {} times, automatic restart gave up", policy.max_restarts);
+        if !stderr.is_empty() {
+            message = format!("{}. It exited during startup:\n{}", message, stderr.join("\n"));
+        }
+        state.fail(app, message);
+        let _ = app.emit("backend-failed", BackendFailedPayload {
+            restart_count: policy.max_restarts,
+            exit_code,
+            reason: "Maximum restart attempts exceeded".into(),
+            stderr,
+        });
+        return;
+    };
+
+    let generation = state.generation.load(Ordering::SeqCst);
+    let delay = policy.delay_for(attempt, generate_seed());
+    state.set_phase(app, BackendPhase::Restarting);
+    let _ = app.emit("backend-restarting", BackendRestartingPayload {
+        attempt,
+        max_restarts: policy.max_restarts,
+        delay_ms: delay.as_millis() as u64,
+        exit_code,
+        stderr,
+    });
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        log::info!(
+            target: LOG_TARGET,
+            "Restarting backend in {}ms (attempt {}/{})",
+            delay.as_millis(),
+            attempt,
+            policy.max_restarts
+        );
+        tokio::time::sleep(delay).await;
+
+        // Bail out if the window closed, someone restarted the backend or restarts were
+        // switched off meanwhile
+        let state = app.state::<BackendState>();
+        if state.shutting_down.load(Ordering::SeqCst)
+            || state.generation.load(Ordering::SeqCst) != generation
+        {
+            return;
+        }
+        if !state.restart_policy.lock_recover().enabled {
+            state.set_phase(&app, BackendPhase::Failed);
+            return;
+        }
+
+        // Drop the dead child and the tasks tied to it
+        for task in state.tasks.lock_recover().drain(..) {
+            task.abort();
+        }
+        state.take_child();
+
+        match spawn_backend(&app).await {
+            Ok(()) => {
+                let _ = app.emit("backend-restarted", BackendRestartedPayload { attempt, exit_code });
+            }
+            Err(e) => {
+                log::error!(target: LOG_TARGET, "{}", e);
+                let _ = app.emit("backend-failed", BackendFailedPayload {
+                    restart_count: attempt,
+                    exit_code,
+                    reason: e,
+                    stderr: Vec::new(),
+                });
+            }
+        }
+    });
 }
 
-/// Wait for backend to be ready by performing health checks with exponential backoff
-async fn wait_for_backend_health(ready_flag: Arc<Mutex<bool>>) {
-    const MAX_ATTEMPTS: u32 = 30;
-    const INITIAL_DELAY_MS: u64 = 200;
-    const MAX_DELAY_MS: u64 = 2000;
-    
+/// Wait for backend to be ready by performing health checks with exponential backoff. The target
+/// is re-read before every attempt, so a corrected address is probed without restarting the loop.
+/// Probing stops as soon as `alive` says the process terminated; the monitor's exit handling,
+/// not this loop, then decides whether the backend is respawned.
+async fn wait_for_backend_health(
+    app: AppHandle,
+    config: HealthCheckConfig,
+    cancel: CancellationToken,
+    alive: ProcessAlive,
+) {
+    let deadline = Instant::now() + config.deadline;
+    let max_attempts = config.expected_attempts();
     let mut attempt = 0;
-    let mut delay = INITIAL_DELAY_MS;
-    
-    while attempt < MAX_ATTEMPTS {
+    let mut delay = config.initial_delay;
+    let mut last_error = None;
+
+    // A probe right after the spawn only finds the backend still importing its modules
+    if !wait_grace(&config, generate_seed(), &cancel).await {
+        return;
+    }
+
+    let mut target = ProbeTarget::new(|| app.state::<BackendState>().target());
+    loop {
+        if !alive.is_alive() {
+            report_died_during_startup(&app, attempt, last_error);
+            return;
+        }
         attempt += 1;
-        
+        let ((host, port), changed) = target.next();
+        if changed {
+            log::info!(target: LOG_TARGET, "Backend address changed, now probing {}:{}", host, port);
+        }
+
         // Check if already marked ready
-        if *ready_flag.lock().unwrap() {
-            println!("✓ Backend health check passed (via log monitoring)");
+        if app.state::<BackendState>().is_ready() {
+            log::info!(target: LOG_TARGET, "Backend health check passed (via log monitoring)");
+            emit_startup_progress(&app, attempt, max_attempts, None, StartupOutcome::Ready);
+            on_backend_ready(&app, &host, port).await;
             return;
         }
-        
-        // Perform HTTP health check
-        match perform_health_check().await {
-            Ok(true) => {
-                *ready_flag.lock().unwrap() = true;
-                println!("✓ Backend health check passed (via HTTP)");
-                return;
+
+        // Perform TCP + HTTP health check, giving up immediately on shutdown or at the deadline
+        let probe = tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = alive.died() => continue,
+            _ = tokio::time::sleep_until(deadline.into()) => break,
+            probe = perform_health_check(&config, &host, port) => probe,
+        };
+        match probe {
+            Ok(HealthOk { ready: true, family }) => {
+                let state = app.state::<BackendState>();
+                state.startup.lock_recover().record_http_ok(Instant::now());
+                *state.address_family.lock_recover() = Some(family);
+                if signal_backend_ready(&app, port, "http") {
+                    log::info!(target: LOG_TARGET, "Backend health check passed (via HTTP over {})", family);
+                }
+                if state.is_ready() {
+                    emit_startup_progress(&app, attempt, max_attempts, None, StartupOutcome::Ready);
+                    on_backend_ready(&app, &host, port).await;
+                    return;
+                }
+                // The policy also wants the log marker, keep polling until the monitor sees it
+                log::info!(target: LOG_TARGET, "Backend answers HTTP, waiting for its readiness marker (attempt {}/{})", attempt, max_attempts);
+                last_error = Some("Backend answered HTTP but never printed its readiness marker".to_string());
             }
-            Ok(false) => {
-                println!("⚠ Backend responded but not ready yet (attempt {}/{})", attempt, MAX_ATTEMPTS);
+            Ok(HealthOk { ready: false, .. }) => {
+                log::info!(target: LOG_TARGET, "Backend responded but not ready yet (attempt {}/{})", attempt, max_attempts);
+                last_error = Some("Backend responded but reported not ready".to_string());
             }
-            Err(_) => {
+            Err(HealthError::ConnectionRefused) => {
+                last_error = Some(format!("Nothing listening on {}:{}", host, port));
                 if attempt == 1 {
-                    println!("⏳ Waiting for backend to start (attempt {}/{})", attempt, MAX_ATTEMPTS);
+                    log::info!(target: LOG_TARGET, "Waiting for backend to start (attempt {}/{})", attempt, max_attempts);
                 }
             }
+            Err(e) => {
+                log::warn!(target: LOG_TARGET, "Backend health check failed: {} (attempt {}/{})", e, attempt, max_attempts);
+                last_error = Some(e.to_string());
+            }
         }
-        
-        // Exponential backoff with max delay
-        tokio::time::sleep(Duration::from_millis(delay)).await;
-        delay = std::cmp::min(delay * 2, MAX_DELAY_MS);
-    }
-    
-    eprintln!("⚠ Backend health check timeout after {} attempts", MAX_ATTEMPTS);
-    eprintln!("  The app will continue, but backend may not be ready");
-    *ready_flag.lock().unwrap() = true; // Mark as ready anyway to unblock
-}
-
-/// Perform a simple health check on the backend
-async fn perform_health_check() -> Result<bool, Box<dyn std::error::Error>> {
-    let urls = [
-        "http://localhost:8000/health",
-        "http://127.0.0.1:8000/health",
-        "http://localhost:8000/docs",
-    ];
-    
-    for url in urls.iter() {
-        match reqwest::Client::new()
-            .get(*url)
-            .timeout(Duration::from_secs(1))
-            .send()
-            .await
-        {
-            Ok(resp) => return Ok(resp.status().is_success()),
-            Err(_) => continue,
+
+        // Exponential backoff with max delay, cut short by the deadline
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let wait = delay.min(remaining);
+        // The estimate ignores probe time, never report a total below the attempts already made
+        emit_startup_progress(&app, attempt, max_attempts.max(attempt + 1), Some(wait), StartupOutcome::Pending);
+        if !wait_retry(wait, &cancel, &alive).await {
+            return;
+        }
+        delay = config.next_delay(delay);
+    }
+
+    log::warn!(
+        target: LOG_TARGET,
+        "Backend health check gave up after {}s ({} attempts), use restart_backend to retry",
+        config.deadline.as_secs(),
+        attempt
+    );
+    app.state::<BackendState>().fail(
+        &app,
+        format!(
+            "Backend did not become ready within {}s: {}",
+            config.deadline.as_secs(),
+            last_error.as_deref().unwrap_or("no answer")
+        ),
+    );
+    emit_startup_progress(&app, attempt, attempt, None, StartupOutcome::Failed);
+    let _ = app.emit("backend-unreachable", BackendUnreachablePayload {
+        attempts: attempt,
+        last_error,
+    });
+}
+
+/// The sidecar terminated while the startup loop was still waiting on it
+fn report_died_during_startup(app: &AppHandle, attempts: u32, last_error: Option<String>) {
+    log::warn!(
+        target: LOG_TARGET,
+        "Backend process died during startup, stopped health checks after {} attempts",
+        attempts
+    );
+    emit_startup_progress(app, attempts, attempts, None, StartupOutcome::Failed);
+    let _ = app.emit("backend-died-during-startup", BackendUnreachablePayload { attempts, last_error });
+}
+
+/// Report health-check progress so the UI can show "attempt 4/30"
+fn emit_startup_progress(
+    app: &AppHandle,
+    attempt: u32,
+    max_attempts: u32,
+    next_delay: Option<Duration>,
+    outcome: StartupOutcome,
+) {
+    let _ = app.emit("backend-startup-progress", StartupProgressPayload {
+        attempt,
+        max_attempts,
+        next_delay_ms: next_delay.map(|delay| delay.as_millis() as u64),
+        outcome,
+    });
+}
+
+/// Record a readiness signal and mark the backend ready once the configured policy is satisfied.
+/// Returns true only for the call that actually marked it ready.
+fn signal_backend_ready(app: &AppHandle, port: u16, method: &'static str) -> bool {
+    let state = app.state::<BackendState>();
+    let policy = state.config().readiness_policy;
+    let satisfied = {
+        let mut signals = state.readiness.lock_recover();
+        signals.record(method);
+        signals.satisfies(policy)
+    };
+    satisfied && mark_backend_ready(app, port, method)
+}
+
+/// Move a starting backend to `Ready` and emit `backend-ready`, returns false if it already was
+/// or isn't starting. A backend that failed, on its preflight check or the health deadline,
+/// stays failed until it is restarted.
+fn mark_backend_ready(app: &AppHandle, port: u16, method: &'static str) -> bool {
+    let state = app.state::<BackendState>();
+    let starting = |phase: BackendPhase| matches!(phase, BackendPhase::Spawning | BackendPhase::WaitingForReady);
+    if !state.transition(app, starting, BackendPhase::Ready) {
+        return false;
+    }
+    *state.last_error.lock_recover() = None;
+    api::reset_breaker();
+    state
+        .startup
+        .lock_recover()
+        .record_ready(Instant::now(), method);
+    let _ = app.emit("backend-ready", BackendReadyPayload {
+        port,
+        method,
+        timestamp: unix_millis(),
+    });
+    emit_backend_url(app);
+    true
+}
+
+/// Milliseconds since the Unix epoch, used to timestamp frontend events
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mock app with the plugins the backend code relies on
+    fn mock_app() -> tauri::App<AppRuntime> {
+        tauri::test::mock_builder()
+            .plugin(tauri_plugin_shell::init())
+            .build(tauri::test::mock_context(tauri::test::noop_assets()))
+            .unwrap()
+    }
+
+    #[test]
+    fn lifecycle_transitions_are_allowed() {
+        use BackendPhase::*;
+        // Start, become ready, go unresponsive and back, crash, restart, stop
+        let lifecycle = [
+            Stopped, PreStart, Spawning, WaitingForReady, Ready, Unresponsive, Ready, Restarting, Spawning,
+            WaitingForReady, Ready, Stopped, Spawning, WaitingForReady, Failed, Spawning,
+        ];
+        for pair in lifecycle.windows(2) {
+            assert!(pair[0].can_become(pair[1]), "{:?} -> {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn failed_backend_stays_failed_until_restarted() {
+        use BackendPhase::*;
+        for next in [WaitingForReady, Ready, Unresponsive, Restarting] {
+            assert!(!Failed.can_become(next), "Failed -> {:?}", next);
+        }
+        for next in [PreStart, Spawning, Stopped] {
+            assert!(Failed.can_become(next), "Failed -> {:?}", next);
+        }
+        // Any other phase may fail
+        for phase in [PreStart, Spawning, WaitingForReady, Ready, Unresponsive, Restarting, Stopped] {
+            assert!(phase.can_become(Failed), "{:?} -> Failed", phase);
+        }
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn busy_port_range_is_an_error() {
+        let first = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let base = first.local_addr().unwrap().port();
+        // Ports that fail to bind here are taken by someone else, which is just as busy
+        let _rest: Vec<_> = (1..4).filter_map(|offset| std::net::TcpListener::bind(("127.0.0.1", base + offset)).ok()).collect();
+
+        let error = scan_ports("127.0.0.1", base, 4).unwrap_err();
+        assert_eq!(error, format!("No free port available for the backend in range {}-{}", base, base + 3));
+        assert!(scan_ports("127.0.0.1", base, 1).is_err());
+
+        drop(first);
+        assert_eq!(scan_ports("127.0.0.1", base, 4), Ok(base));
+    }
+
+    #[cfg(all(desktop, unix))]
+    #[test]
+    fn child_is_stored_and_taken() {
+        let app = mock_app();
+        let (_rx, child) = app.shell().command("sleep").args(["30"]).spawn().unwrap();
+        let pid = child.pid();
+        let state = BackendState::new(BackendConfig::default());
+        *state.child.lock_recover() = Some(child);
+        *state.pid.lock_recover() = Some(pid);
+
+        let taken = state.take_child().unwrap();
+        assert_eq!(taken.pid(), pid);
+        assert!(state.pid.lock_recover().is_none());
+        assert!(state.take_child().is_none());
+        taken.kill().unwrap();
+    }
+
+    #[test]
+    fn restart_delays_grow_up_to_the_cap() {
+        let policy = RestartPolicy {
+            max_restarts: 10,
+            backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            jitter_ms: 250,
+            ..RestartPolicy::default()
+        };
+        let millis = |attempt, roll| policy.delay_for(attempt, roll).as_millis() as u64;
+        let plain: Vec<_> = (1..=8).map(|attempt| millis(attempt, 0)).collect();
+        assert_eq!(plain, [500, 1000, 2000, 4000, 8000, 10_000, 10_000, 10_000]);
+        // Jitter adds up to `jitter_ms` on top, but never past the cap
+        assert_eq!(millis(1, 250), 750);
+        assert_eq!(millis(1, 251), 500);
+        assert_eq!(millis(5, 250), 8250);
+        assert_eq!(millis(6, 250), 10_000);
+        assert_eq!(millis(u32::MAX, u64::MAX), 10_000);
+
+        // Successive simulated crashes, with whatever jitter they roll
+        let mut count = 0;
+        let mut previous = 0;
+        while let Ok(attempt) = policy.claim_attempt(&mut count) {
+            let delay = millis(attempt, generate_seed());
+            let floor = plain[(attempt as usize - 1).min(plain.len() - 1)];
+            assert!((floor..=10_000).contains(&delay), "attempt {}: {}ms", attempt, delay);
+            assert!(delay + policy.jitter_ms >= previous, "attempt {} shrank to {}ms", attempt, delay);
+            previous = delay;
+        }
+        assert_eq!(count, 10);
+    }
+
+    #[test]
+    fn restart_policies_are_validated() {
+        let valid = RestartPolicy::default();
+        assert!(valid.validate().is_ok());
+        for invalid in [
+            RestartPolicy { backoff_ms: 0, ..valid },
+            RestartPolicy { backoff_ms: MAX_RESTART_DELAY_MS + 1, ..valid },
+            RestartPolicy { max_backoff_ms: valid.backoff_ms - 1, ..valid },
+            RestartPolicy { jitter_ms: valid.max_backoff_ms + 1, ..valid },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn failures_are_kept_until_the_backend_is_ready() {
+        let app = mock_app();
+        app.manage(BackendState::new(BackendConfig::default()));
+        let handle = app.handle();
+        let state = handle.state::<BackendState>();
+        let last_error = || backend_status(&state).last_error.map(|error| error.message);
+        assert_eq!(last_error(), None);
+
+        // A crash during startup, then the health deadline on the next attempt
+        state.set_phase(handle, BackendPhase::WaitingForReady);
+        state.record_error("Backend exited during startup (exit code 3):\nRuntimeError: engine import failed");
+        assert!(last_error().unwrap().contains("RuntimeError"));
+        state.fail(handle, "Backend did not become ready within 30s: nothing listening");
+        assert_eq!(state.phase(), BackendPhase::Failed);
+        assert_eq!(last_error().as_deref(), Some("Backend did not become ready within 30s: nothing listening"));
+        assert!(backend_status(&state).last_error.unwrap().timestamp > 0);
+
+        // A failed backend can't turn ready behind the user's back, so the error stays
+        assert!(!mark_backend_ready(handle, 8000, "http"));
+        assert!(last_error().is_some());
+
+        // Restarted, becoming ready clears it
+        state.set_phase(handle, BackendPhase::Spawning);
+        assert!(mark_backend_ready(handle, 8000, "log"));
+        assert_eq!(last_error(), None);
+        assert!(state.is_ready());
+    }
+
+    #[test]
+    fn restarts_are_forgiven_after_a_stable_run() {
+        let state = BackendState::new(BackendConfig::default());
+        let spawned = Instant::now();
+        *state.startup.lock_recover() = StartupMetrics::begin(spawned);
+        *state.restart_count.lock_recover() = 3;
+
+        // Crashing before ever becoming ready counts against the limit
+        assert_eq!(state.forgive_restarts(spawned + STABLE_UPTIME * 2), 0);
+        assert_eq!(*state.restart_count.lock_recover(), 3);
+
+        let ready = spawned + Duration::from_secs(2);
+        state.startup.lock_recover().record_ready(ready, "log");
+        assert_eq!(state.forgive_restarts(ready + STABLE_UPTIME / 2), 0);
+        assert_eq!(*state.restart_count.lock_recover(), 3);
+        assert_eq!(state.forgive_restarts(ready + STABLE_UPTIME), 3);
+        assert_eq!(*state.restart_count.lock_recover(), 0);
+
+        // The next crash then starts over at the first attempt
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.claim_attempt(&mut state.restart_count.lock_recover()), Ok(1));
+    }
+
+    #[test]
+    fn artifact_cache_evicts_the_oldest_session() {
+        let artifact = |name: &str| Artifact {
+            name: name.to_string(),
+            size: 128,
+            kind: "text/csv".to_string(),
+            path: api::result_path("5f1c0a9e", name),
+        };
+        let mut cache = ArtifactCache::default();
+        for n in 0..MAX_CACHED_ARTIFACT_SESSIONS {
+            cache.insert(format!("session-{}", n), Vec::new());
+        }
+        // Refreshing a session makes it the newest
+        cache.insert("session-0".to_string(), vec![artifact("bits.csv")]);
+        cache.insert("session-new".to_string(), Vec::new());
+
+        assert!(cache.get("session-1").is_none());
+        assert_eq!(cache.get("session-0").unwrap()[0].name, "bits.csv");
+        assert!(cache.get("session-2").is_some() && cache.get("session-new").is_some());
+        assert_eq!(cache.0.len(), MAX_CACHED_ARTIFACT_SESSIONS);
+
+        cache.remove("session-0");
+        assert!(cache.get("session-0").is_none());
+        cache.clear();
+        assert!(cache.get("session-new").is_none());
+    }
+
+    #[test]
+    fn disabled_policy_never_restarts() {
+        let state = BackendState::new(BackendConfig::default());
+        *state.restart_policy.lock_recover() = RestartPolicy {
+            enabled: false,
+            ..RestartPolicy::default()
+        };
+        // Each simulated termination asks the policy the way `schedule_restart` does
+        let terminate = || state.restart_policy.lock_recover().claim_attempt(&mut state.restart_count.lock_recover());
+        for _ in 0..3 {
+            assert_eq!(terminate(), Err(NoRestart::Disabled));
         }
+        assert_eq!(*state.restart_count.lock_recover(), 0);
+
+        // Switched on at runtime, the next termination is restarted up to the limit
+        *state.restart_policy.lock_recover() = RestartPolicy {
+            max_restarts: 2,
+            ..RestartPolicy::default()
+        };
+        assert_eq!(terminate(), Ok(1));
+        assert_eq!(terminate(), Ok(2));
+        assert_eq!(terminate(), Err(NoRestart::Exhausted));
+
+        // And off again, without counting the refused restart
+        state.restart_policy.lock_recover().enabled = false;
+        *state.restart_count.lock_recover() = 0;
+        assert_eq!(terminate(), Err(NoRestart::Disabled));
+        assert_eq!(*state.restart_count.lock_recover(), 0);
     }
-    
-    Err("No health endpoints responded".into())
 }
@@ -0,0 +1,955 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::config::duration_ms;
+use crate::sync::LockExt;
+use crate::{AppHandle, BackendState, BACKEND_OUTPUT_TARGET, LOG_TARGET};
+
+// Lines waiting for the output worker before new ones are dropped
+const OUTPUT_QUEUE_CAPACITY: usize = 4096;
+
+/// Which pipe of the backend process a line came from
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// Severity of a backend output line, parsed from its log-level prefix
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    /// Find a level name among the first few tokens of a line, which covers uvicorn's
+    /// `INFO:     ...`, Python logging's `WARNING:root:...` and `2024-01-01 ... - ERROR - ...`,
+    /// bracketed `[ERROR] ...` styles and the `warnings` module's `file.py:3: UserWarning: ...`.
+    /// Timestamps and line numbers are skipped so they don't use up the tokens looked at.
+    pub fn parse(line: &str) -> Option<Self> {
+        line.split(|c: char| c.is_whitespace() || matches!(c, ':' | '[' | ']' | '|'))
+            .filter(|token| !token.is_empty() && *token != "-" && !token.starts_with(|c: char| c.is_ascii_digit()))
+            .take(4)
+            .find_map(|token| match token {
+                "DEBUG" | "TRACE" => Some(Self::Debug),
+                "INFO" => Some(Self::Info),
+                "WARNING" | "WARN" => Some(Self::Warning),
+                "ERROR" => Some(Self::Error),
+                "CRITICAL" | "FATAL" => Some(Self::Critical),
+                _ if token.ends_with("Warning") => Some(Self::Warning),
+                _ => None,
+            })
+    }
+
+    /// Level for a line, assuming stdout is informational and an unknown stderr line is an error
+    pub fn classify(stream: LogStream, line: &str) -> Self {
+        Self::parse(line).unwrap_or(match stream {
+            LogStream::Stdout => Self::Info,
+            LogStream::Stderr => Self::Error,
+        })
+    }
+
+    /// The `log` crate level used when echoing the line into the app log
+    pub fn as_log_level(&self) -> log::Level {
+        match self {
+            Self::Debug => log::Level::Debug,
+            Self::Info => log::Level::Info,
+            Self::Warning => log::Level::Warn,
+            Self::Error | Self::Critical => log::Level::Error,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// A single line of backend output
+#[derive(Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: u64,
+    pub stream: LogStream,
+    pub level: LogLevel,
+    // Raw text as printed by the backend
+    pub line: String,
+}
+
+impl LogLine {
+    pub fn new(stream: LogStream, line: impl Into<String>) -> Self {
+        let line = line.into();
+        Self {
+            timestamp: crate::unix_millis(),
+            stream,
+            level: LogLevel::classify(stream, &line),
+            line,
+        }
+    }
+}
+
+/// Selects buffered lines by text and severity, see `LogBuffer::matching`
+pub struct LineFilter {
+    // Lowercased and wrapped in `*` so it can match anywhere in a line
+    pattern: Vec<char>,
+    min_level: Option<LogLevel>,
+}
+
+impl LineFilter {
+    /// Lines containing `pattern`, ignoring case, where `*` and `?` are wildcards, and at
+    /// `min_level` or above when given
+    pub fn new(pattern: &str, min_level: Option<LogLevel>) -> Self {
+        let pattern = format!("*{}*", pattern.to_lowercase()).chars().collect();
+        Self { pattern, min_level }
+    }
+
+    pub fn matches(&self, line: &LogLine) -> bool {
+        if self.min_level.is_some_and(|min| line.level < min) {
+            return false;
+        }
+        let text: Vec<char> = line.line.to_lowercase().chars().collect();
+        glob_match(&self.pattern, &text)
+    }
+}
+
+/// Match `text` against a pattern where `*` is any run of characters and `?` any one
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*` and the text position it is currently matched up to
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and retry from there
+                Some((after_star, matched)) => {
+                    star = Some((after_star, matched + 1));
+                    p = after_star;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Bounded buffer of the most recent backend output, oldest lines are evicted first
+pub struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Change the capacity, dropping the oldest lines if the buffer shrinks
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.lines.len() > capacity {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn push(&mut self, line: LogLine) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn snapshot(&self) -> Vec<LogLine> {
+        self.lines.iter().cloned().collect()
+    }
+
+    /// Remove and return every buffered line, oldest first
+    pub fn take(&mut self) -> Vec<LogLine> {
+        self.lines.drain(..).collect()
+    }
+
+    /// Drop every buffered line, returning how many there were. The capacity is kept, so
+    /// output arriving afterwards is buffered as before.
+    pub fn clear(&mut self) -> usize {
+        let cleared = self.lines.len();
+        self.lines.clear();
+        cleared
+    }
+
+    /// The last `count` lines classified as errors or worse, newest first
+    pub fn recent_errors(&self, count: usize) -> Vec<LogLine> {
+        self.lines
+            .iter()
+            .rev()
+            .filter(|line| matches!(line.level, LogLevel::Error | LogLevel::Critical))
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// The last `count` lines accepted by `filter`, newest first
+    pub fn matching(&self, filter: &LineFilter, count: usize) -> Vec<LogLine> {
+        self.lines
+            .iter()
+            .rev()
+            .filter(|line| filter.matches(line))
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// The last `count` stderr lines, oldest first
+    pub fn recent_stderr(&self, count: usize) -> Vec<String> {
+        let mut recent: Vec<String> = self
+            .lines
+            .iter()
+            .rev()
+            .filter(|line| matches!(line.stream, LogStream::Stderr))
+            .take(count)
+            .map(|line| line.line.clone())
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
+
+/// When runs of identical lines are collapsed, see `RepeatFilter`
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepeatConfig {
+    // Identical lines kept before further repeats are only counted, 0 keeps every line
+    pub threshold: usize,
+    // Repeats are collapsed within this long of the first line of a run, after that the line
+    // shows up again so a steady message never disappears for good
+    #[serde(rename = "window_ms", with = "duration_ms")]
+    pub window: Duration,
+}
+
+impl Default for RepeatConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 3,
+            window: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Collapses consecutive identical lines from a spamming backend, e.g. a reconnect loop, into
+/// a single `… (repeated N times)` line so they don't flood the buffer, log file and frontend
+pub struct RepeatFilter {
+    config: RepeatConfig,
+    // First line of the current run, and how many times it has been seen since
+    run: Option<LogLine>,
+    seen: usize,
+}
+
+impl RepeatFilter {
+    pub fn new(config: RepeatConfig) -> Self {
+        Self {
+            config,
+            run: None,
+            seen: 0,
+        }
+    }
+
+    /// The lines to keep now that `line` arrived: nothing while a run is being collapsed,
+    /// otherwise `line` itself, preceded by the summary of a run it just ended
+    pub fn push(&mut self, line: LogLine) -> Vec<LogLine> {
+        if self.config.threshold == 0 {
+            return vec![line];
+        }
+        let window = self.config.window.as_millis() as u64;
+        let repeat = self.run.as_ref().is_some_and(|run| {
+            run.stream == line.stream
+                && run.line == line.line
+                && line.timestamp.saturating_sub(run.timestamp) < window
+        });
+        if repeat {
+            self.seen += 1;
+            return if self.seen <= self.config.threshold { vec![line] } else { Vec::new() };
+        }
+
+        let mut keep: Vec<_> = self.flush().into_iter().collect();
+        self.run = Some(line.clone());
+        self.seen = 1;
+        keep.push(line);
+        keep
+    }
+
+    /// End the current run, returning its summary if any repeats were collapsed
+    pub fn flush(&mut self) -> Option<LogLine> {
+        let run = self.run.take()?;
+        let collapsed = std::mem::take(&mut self.seen).saturating_sub(self.config.threshold);
+        (collapsed > 0).then(|| LogLine {
+            timestamp: crate::unix_millis(),
+            stream: run.stream,
+            level: run.level,
+            line: format!("… (repeated {} times)", collapsed),
+        })
+    }
+}
+
+/// The first stderr lines of a freshly spawned backend. A backend that dies on a bad argument
+/// or an import error says why right at the start, long before the buffer's tail.
+pub struct EarlyStderr {
+    lines: Vec<String>,
+    limit: usize,
+}
+
+impl EarlyStderr {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            lines: Vec::new(),
+            limit,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.lines.len() >= self.limit
+    }
+
+    /// Keep `line` unless enough were captured already, blank lines are skipped
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end();
+        if !self.is_full() && !line.trim().is_empty() {
+            self.lines.push(line.to_string());
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Error message for a backend that exited with `exit` before it became ready
+    pub fn describe_exit(&self, exit: &str) -> String {
+        if self.lines.is_empty() {
+            format!("Backend exited during startup ({}) without printing an error", exit)
+        } else {
+            format!("Backend exited during startup ({}):\n{}", exit, self.lines.join("\n"))
+        }
+    }
+}
+
+/// Hands backend output from the monitor loop to the output worker without ever waiting.
+/// If the worker falls behind, lines are dropped and counted so the sidecar can't block
+/// on a full pipe; the ring buffer still gets every line.
+pub struct OutputQueue {
+    tx: tokio::sync::mpsc::Sender<LogLine>,
+    dropped: u64,
+}
+
+impl OutputQueue {
+    /// Create the queue and the receiving end for `forward_logs`
+    pub fn new() -> (Self, tokio::sync::mpsc::Receiver<LogLine>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(OUTPUT_QUEUE_CAPACITY);
+        (Self { tx, dropped: 0 }, rx)
+    }
+
+    pub fn push(&mut self, line: LogLine) {
+        match self.tx.try_send(line) {
+            Ok(()) if self.dropped > 0 => {
+                log::warn!(
+                    target: LOG_TARGET,
+                    "Backend output outpaced the log worker, dropped {} lines",
+                    self.dropped
+                );
+                self.dropped = 0;
+            }
+            Ok(()) | Err(TrySendError::Closed(_)) => {}
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+        }
+    }
+}
+
+/// Output worker: echo each line into the app log and the log file, and forward it to the
+/// frontend as `backend-log` events unless forwarding is paused. Lines arriving within
+/// `interval` of the first one are coalesced into a single event.
+pub async fn forward_logs(
+    app: AppHandle,
+    rx: tokio::sync::mpsc::Receiver<LogLine>,
+    interval: Duration,
+) {
+    let state = app.state::<BackendState>();
+    forward_batches(
+        rx,
+        interval,
+        &state.log_forwarding,
+        |line| record(&app, line),
+        |batch| {
+            let _ = app.emit("backend-log", batch);
+        },
+    )
+    .await;
+}
+
+/// Batching behind `forward_logs`: every line is recorded, batches are only emitted while
+/// `forwarding` is set
+async fn forward_batches(
+    mut rx: tokio::sync::mpsc::Receiver<LogLine>,
+    interval: Duration,
+    forwarding: &AtomicBool,
+    mut record: impl FnMut(&LogLine),
+    mut emit: impl FnMut(Vec<LogLine>),
+) {
+    while let Some(first) = rx.recv().await {
+        record(&first);
+        let mut batch = vec![first];
+        let window = tokio::time::sleep(interval);
+        tokio::pin!(window);
+        loop {
+            tokio::select! {
+                _ = &mut window => break,
+                line = rx.recv() => match line {
+                    Some(line) => {
+                        record(&line);
+                        batch.push(line);
+                    }
+                    None => break,
+                },
+            }
+        }
+        if forwarding.load(Ordering::SeqCst) {
+            emit(batch);
+        }
+    }
+}
+
+fn record(app: &AppHandle, line: &LogLine) {
+    // Stdout is logged as info whatever it says, only stderr lines carry their own level
+    let level = match line.stream {
+        LogStream::Stdout => log::Level::Info,
+        LogStream::Stderr => line.level.as_log_level(),
+    };
+    log::log!(target: BACKEND_OUTPUT_TARGET, level, "{}", line.line);
+    if let Some(sink) = app.state::<BackendState>().log_file.lock_recover().as_ref() {
+        sink.write(line);
+    }
+}
+
+/// Settings for teeing backend output to rotating files in the app log directory
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogFileConfig {
+    pub enabled: bool,
+    // Rotate once the active file reaches this size
+    pub max_bytes: u64,
+    // Rotated files kept besides the active one
+    pub max_files: usize,
+    // `crash-<ts>.log` files kept, 0 disables saving the buffer when the backend crashes
+    pub max_crash_logs: usize,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_bytes: 5 * 1024 * 1024,
+            max_files: 3,
+            max_crash_logs: 5,
+        }
+    }
+}
+
+/// Writes backend output to `backend.log` on a background thread so the recv loop never
+/// waits on disk, rotating to `backend.log.1` .. `backend.log.N` as files fill up
+pub struct LogFileSink {
+    path: PathBuf,
+    tx: mpsc::Sender<LogLine>,
+}
+
+impl LogFileSink {
+    pub fn spawn(dir: &Path, config: &LogFileConfig) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join("backend.log");
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        let (tx, rx) = mpsc::channel::<LogLine>();
+        let writer = RotatingWriter {
+            path: path.clone(),
+            max_bytes: config.max_bytes,
+            max_files: config.max_files,
+            out: BufWriter::new(file),
+            written,
+        };
+        std::thread::Builder::new()
+            .name("backend-log-file".into())
+            .spawn(move || writer.run(rx))?;
+
+        Ok(Self { path, tx })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, line: &LogLine) {
+        let _ = self.tx.send(line.clone());
+    }
+}
+
+struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    out: BufWriter<File>,
+    written: u64,
+}
+
+impl RotatingWriter {
+    fn run(mut self, rx: mpsc::Receiver<LogLine>) {
+        while let Ok(line) = rx.recv() {
+            if let Err(e) = self.write_line(&line) {
+                log::warn!(target: LOG_TARGET, "Failed to write backend log file: {}", e);
+            }
+            // Flush whenever we catch up so the file is current without a write per line
+            while let Ok(line) = rx.try_recv() {
+                if let Err(e) = self.write_line(&line) {
+                    log::warn!(target: LOG_TARGET, "Failed to write backend log file: {}", e);
+                }
+            }
+            let _ = self.out.flush();
+        }
+    }
+
+    fn write_line(&mut self, line: &LogLine) -> std::io::Result<()> {
+        let entry = format_line(line);
+        self.out.write_all(entry.as_bytes())?;
+        self.written += entry.len() as u64;
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.out.flush()?;
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(self.max_files));
+            for n in (1..self.max_files).rev() {
+                let _ = fs::rename(rotated(n), rotated(n + 1));
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+        self.out = BufWriter::new(File::create(&self.path)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// One line of a log file, `<timestamp> [<stream>] [<level>] <text>`
+fn format_line(line: &LogLine) -> String {
+    format!(
+        "{} [{}] [{}] {}\n",
+        line.timestamp,
+        line.stream.as_str(),
+        line.level.as_str(),
+        line.line
+    )
+}
+
+/// A saved `crash-<ts>.log` file
+#[derive(Serialize)]
+pub struct CrashLog {
+    pub path: String,
+    // Milliseconds since the Unix epoch when the backend exited
+    pub timestamp: u64,
+    pub size: u64,
+}
+
+/// Write the lines buffered before a crash to `crash-<timestamp>.log` in `dir`, then delete
+/// the oldest crash logs so at most `keep` remain
+pub fn write_crash_log(dir: &Path, timestamp: u64, lines: &[LogLine], keep: usize) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}.log", timestamp));
+    let mut out = BufWriter::new(File::create(&path)?);
+    for line in lines {
+        out.write_all(format_line(line).as_bytes())?;
+    }
+    out.flush()?;
+
+    for old in list_crash_logs(dir)?.into_iter().skip(keep) {
+        let _ = fs::remove_file(old.path);
+    }
+    Ok(path)
+}
+
+/// Delete every crash log in `dir`, returning how many were removed
+pub fn delete_crash_logs(dir: &Path) -> std::io::Result<usize> {
+    let logs = list_crash_logs(dir)?;
+    for log in &logs {
+        fs::remove_file(&log.path)?;
+    }
+    Ok(logs.len())
+}
+
+/// Crash logs in `dir`, newest first
+pub fn list_crash_logs(dir: &Path) -> std::io::Result<Vec<CrashLog>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut logs: Vec<CrashLog> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let timestamp = name.to_str()?.strip_prefix("crash-")?.strip_suffix(".log")?.parse().ok()?;
+            Some(CrashLog {
+                path: entry.path().display().to_string(),
+                timestamp,
+                size: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect();
+    logs.sort_by_key(|log| std::cmp::Reverse(log.timestamp));
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> LogBuffer {
+        let mut buffer = LogBuffer::new(16);
+        for (stream, line) in [
+            (LogStream::Stderr, "INFO:     Application startup complete."),
+            (LogStream::Stderr, "ERROR:root:QBER 0.31 above threshold for run 1"),
+            (LogStream::Stdout, "simulated 10000 photons, QBER 0.02"),
+            (LogStream::Stderr, "WARNING:root:qber estimate unstable"),
+            (LogStream::Stderr, "ERROR:root:sweep point 4 failed"),
+            (LogStream::Stderr, "ERROR:root:QBER 0.29 above threshold for run 2"),
+        ] {
+            buffer.push(LogLine::new(stream, line));
+        }
+        buffer
+    }
+
+    fn lines(matches: Vec<LogLine>) -> Vec<String> {
+        matches.into_iter().map(|line| line.line).collect()
+    }
+
+    #[test]
+    fn only_errors_are_picked_from_a_mixed_buffer() {
+        let mut buffer = buffer();
+        buffer.push(LogLine::new(LogStream::Stderr, "CRITICAL:root:engine lost its key pool"));
+        buffer.push(LogLine::new(LogStream::Stdout, "sweep finished in 2.4s"));
+
+        let errors = buffer.recent_errors(10);
+        assert_eq!(
+            lines(errors.clone()),
+            [
+                "CRITICAL:root:engine lost its key pool",
+                "ERROR:root:QBER 0.29 above threshold for run 2",
+                "ERROR:root:sweep point 4 failed",
+                "ERROR:root:QBER 0.31 above threshold for run 1",
+            ]
+        );
+        assert!(errors.iter().all(|line| line.stream == LogStream::Stderr && line.timestamp > 0));
+        assert!(errors.windows(2).all(|pair| pair[0].timestamp >= pair[1].timestamp));
+
+        // The limit keeps the newest
+        assert_eq!(lines(buffer.recent_errors(2)), lines(errors[..2].to_vec()));
+        assert!(buffer.recent_errors(0).is_empty());
+        // Unprefixed stderr counts as an error, warnings and stdout never do
+        let mut quiet = LogBuffer::new(8);
+        quiet.push(LogLine::new(LogStream::Stdout, "simulated 10000 photons"));
+        quiet.push(LogLine::new(LogStream::Stderr, "WARNING:root:qber estimate unstable"));
+        assert!(quiet.recent_errors(10).is_empty());
+        quiet.push(LogLine::new(LogStream::Stderr, "Segmentation fault"));
+        assert_eq!(lines(quiet.recent_errors(10)), ["Segmentation fault"]);
+    }
+
+    #[test]
+    fn stderr_formats_are_classified() {
+        for (line, level) in [
+            ("INFO:     Started server process [4242]", LogLevel::Info),
+            ("DEBUG:asyncio:Using selector: EpollSelector", LogLevel::Debug),
+            ("WARNING:root:qber estimate unstable", LogLevel::Warning),
+            ("2024-05-01 12:00:03,417 - qkd.engine - ERROR - sweep point 4 failed", LogLevel::Error),
+            ("[2024-05-01 12:00:03 +0000] [4242] [CRITICAL] WORKER TIMEOUT (pid:4243)", LogLevel::Critical),
+            ("[WARN] slow simulation step", LogLevel::Warning),
+            ("/opt/qkd/engine.py:88: DeprecationWarning: np.float is deprecated", LogLevel::Warning),
+            ("ERROR:    Exception in ASGI application", LogLevel::Error),
+        ] {
+            assert_eq!(LogLevel::classify(LogStream::Stderr, line), level, "{}", line);
+        }
+
+        // Unprefixed output is an error on stderr but plain information on stdout
+        for line in ["Traceback (most recent call last):", "  File \"main.py\", line 12, in <module>", ""] {
+            assert_eq!(LogLevel::parse(line), None, "{}", line);
+            assert_eq!(LogLevel::classify(LogStream::Stderr, line), LogLevel::Error);
+            assert_eq!(LogLevel::classify(LogStream::Stdout, line), LogLevel::Info);
+        }
+        // A level name deep inside the message isn't a prefix
+        assert_eq!(LogLevel::parse("simulated 10000 photons over a noisy channel, no ERROR"), None);
+    }
+
+    #[test]
+    fn full_buffer_drops_the_oldest_lines() {
+        let mut buffer = LogBuffer::new(3);
+        for n in 1..=5 {
+            buffer.push(LogLine::new(LogStream::Stdout, format!("line {}", n)));
+        }
+        assert_eq!(lines(buffer.snapshot()), ["line 3", "line 4", "line 5"]);
+
+        buffer.set_capacity(2);
+        assert_eq!(lines(buffer.snapshot()), ["line 4", "line 5"]);
+        buffer.push(LogLine::new(LogStream::Stdout, "line 6"));
+        assert_eq!(lines(buffer.take()), ["line 5", "line 6"]);
+
+        let mut disabled = LogBuffer::new(0);
+        disabled.push(LogLine::new(LogStream::Stdout, "line 7"));
+        assert!(disabled.snapshot().is_empty());
+    }
+
+    #[test]
+    fn early_stderr_keeps_the_first_lines() {
+        let mut early = EarlyStderr::new(2);
+        assert!(early.describe_exit("exit code 1").ends_with("without printing an error"));
+        for line in ["usage: main.py [--port PORT]\n", "", "main.py: error: unrecognized arguments: --prot", "later noise"] {
+            early.push(line);
+        }
+        assert!(early.is_full());
+        assert_eq!(early.lines(), ["usage: main.py [--port PORT]", "main.py: error: unrecognized arguments: --prot"]);
+        assert_eq!(
+            early.describe_exit("exit code 2"),
+            "Backend exited during startup (exit code 2):\nusage: main.py [--port PORT]\nmain.py: error: unrecognized arguments: --prot"
+        );
+    }
+
+    #[test]
+    fn substring_matches_newest_first_ignoring_case() {
+        let matches = buffer().matching(&LineFilter::new("qber", None), 10);
+        assert_eq!(
+            lines(matches),
+            [
+                "ERROR:root:QBER 0.29 above threshold for run 2",
+                "WARNING:root:qber estimate unstable",
+                "simulated 10000 photons, QBER 0.02",
+                "ERROR:root:QBER 0.31 above threshold for run 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn severity_and_limit_narrow_the_matches() {
+        let buffer = buffer();
+        let errors = buffer.matching(&LineFilter::new("QBER", Some(LogLevel::Error)), 10);
+        assert_eq!(
+            lines(errors),
+            [
+                "ERROR:root:QBER 0.29 above threshold for run 2",
+                "ERROR:root:QBER 0.31 above threshold for run 1",
+            ]
+        );
+        let warnings = buffer.matching(&LineFilter::new("qber", Some(LogLevel::Warning)), 2);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1].line, "WARNING:root:qber estimate unstable");
+        assert!(warnings.iter().all(|line| line.timestamp > 0));
+    }
+
+    #[test]
+    fn wildcards_match_within_a_line() {
+        let buffer = buffer();
+        assert_eq!(lines(buffer.matching(&LineFilter::new("run ?", None), 10)).len(), 2);
+        assert_eq!(
+            lines(buffer.matching(&LineFilter::new("sweep*failed", None), 10)),
+            ["ERROR:root:sweep point 4 failed"]
+        );
+        assert!(buffer.matching(&LineFilter::new("sweep*QBER", None), 10).is_empty());
+        assert_eq!(buffer.matching(&LineFilter::new("", None), 10).len(), 6);
+    }
+
+    #[test]
+    fn cleared_buffer_keeps_accumulating() {
+        let mut buffer = buffer();
+        assert_eq!(buffer.clear(), 6);
+        assert!(buffer.snapshot().is_empty());
+        assert_eq!(buffer.clear(), 0);
+
+        buffer.push(LogLine::new(LogStream::Stdout, "simulated 500 photons, QBER 0.01"));
+        assert_eq!(lines(buffer.snapshot()), ["simulated 500 photons, QBER 0.01"]);
+    }
+
+    #[test]
+    fn crash_logs_can_be_deleted() {
+        let dir = std::env::temp_dir().join(format!("qkd-lab-crash-logs-{}", std::process::id()));
+        let lines = buffer().snapshot();
+        for timestamp in [1_000, 2_000] {
+            write_crash_log(&dir, timestamp, &lines, 5).unwrap();
+        }
+        std::fs::write(dir.join("backend.log"), "kept").unwrap();
+
+        assert_eq!(delete_crash_logs(&dir).unwrap(), 2);
+        assert!(list_crash_logs(&dir).unwrap().is_empty());
+        assert!(dir.join("backend.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(delete_crash_logs(&dir).unwrap(), 0);
+    }
+
+    fn repeated(count: usize, timestamp: u64) -> impl Iterator<Item = LogLine> {
+        (0..count).map(move |_| LogLine {
+            timestamp,
+            ..LogLine::new(LogStream::Stderr, "WARNING:root:reconnecting to detector")
+        })
+    }
+
+    #[test]
+    fn identical_lines_collapse_past_the_threshold() {
+        let mut filter = RepeatFilter::new(RepeatConfig::default());
+        let mut kept: Vec<_> = repeated(100, 1_000).flat_map(|line| filter.push(line)).collect();
+        kept.extend(filter.push(LogLine::new(LogStream::Stderr, "INFO:     reconnected")));
+
+        assert_eq!(
+            lines(kept.clone()),
+            [
+                "WARNING:root:reconnecting to detector",
+                "WARNING:root:reconnecting to detector",
+                "WARNING:root:reconnecting to detector",
+                "… (repeated 97 times)",
+                "INFO:     reconnected",
+            ]
+        );
+        assert_eq!(kept[3].level, LogLevel::Warning);
+    }
+
+    #[test]
+    fn repeats_show_again_once_the_window_passes() {
+        let mut filter = RepeatFilter::new(RepeatConfig::default());
+        let mut kept: Vec<_> = repeated(5, 1_000).flat_map(|line| filter.push(line)).collect();
+        kept.extend(repeated(5, 20_000).flat_map(|line| filter.push(line)));
+        kept.extend(filter.flush());
+
+        let summaries: Vec<_> = lines(kept).into_iter().filter(|line| line.starts_with('…')).collect();
+        assert_eq!(summaries, ["… (repeated 2 times)", "… (repeated 2 times)"]);
+    }
+
+    #[test]
+    fn zero_threshold_keeps_every_line() {
+        let config = RepeatConfig {
+            threshold: 0,
+            ..RepeatConfig::default()
+        };
+        let mut filter = RepeatFilter::new(config);
+        assert_eq!(repeated(10, 1_000).flat_map(|line| filter.push(line)).count(), 10);
+        assert!(filter.flush().is_none());
+    }
+
+    #[tokio::test]
+    async fn paused_forwarding_still_records() {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let forwarding = AtomicBool::new(true);
+        let mut recorded = LogBuffer::new(16);
+        let mut emitted = Vec::new();
+
+        let send = |line| LogLine::new(LogStream::Stdout, line);
+        let feed = async {
+            let pause = Duration::from_millis(100);
+            tx.send(send("photon batch 1")).await.unwrap();
+            tokio::time::sleep(pause).await;
+            forwarding.store(false, Ordering::SeqCst);
+            tx.send(send("photon batch 2")).await.unwrap();
+            tokio::time::sleep(pause).await;
+            forwarding.store(true, Ordering::SeqCst);
+            tx.send(send("photon batch 3")).await.unwrap();
+            drop(tx);
+        };
+        let forward = forward_batches(
+            rx,
+            Duration::from_millis(10),
+            &forwarding,
+            |line| recorded.push(line.clone()),
+            |batch| emitted.push(lines(batch)),
+        );
+        tokio::join!(feed, forward);
+
+        assert_eq!(emitted, [["photon batch 1"], ["photon batch 3"]]);
+        assert_eq!(lines(recorded.snapshot()), ["photon batch 1", "photon batch 2", "photon batch 3"]);
+    }
+
+    #[tokio::test]
+    async fn flooded_output_keeps_draining() {
+        let (mut queue, rx) = OutputQueue::new();
+        let line = |n: usize| LogLine::new(LogStream::Stdout, format!("photon {} detected", n));
+
+        // A stalled worker costs lines, never a wait in the monitor loop
+        let started = std::time::Instant::now();
+        for n in 0..OUTPUT_QUEUE_CAPACITY * 3 {
+            queue.push(line(n));
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(queue.dropped, 2 * OUTPUT_QUEUE_CAPACITY as u64);
+
+        // A running worker keeps up with a flood that gives it a turn now and then
+        let flood = 50_000;
+        let forwarding = AtomicBool::new(true);
+        let mut recorded = 0;
+        let mut emitted = 0;
+        let feed = async {
+            for n in 0..flood {
+                if n % 64 == 0 {
+                    tokio::task::yield_now().await;
+                }
+                queue.push(line(n));
+            }
+            assert_eq!(queue.dropped, 0);
+            drop(queue);
+        };
+        let forward = forward_batches(
+            rx,
+            Duration::from_millis(5),
+            &forwarding,
+            |_| recorded += 1,
+            |batch| emitted += batch.len(),
+        );
+        tokio::join!(feed, forward);
+
+        assert_eq!(recorded, OUTPUT_QUEUE_CAPACITY + flood);
+        assert_eq!(emitted, recorded);
+    }
+}
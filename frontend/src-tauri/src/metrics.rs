@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{duration_ms, env_or};
+
+// Fastest `backend-metrics` cadence allowed, whatever is configured
+const MIN_METRICS_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Settings for the periodic `backend-metrics` event behind the status badge
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsEventConfig {
+    pub enabled: bool,
+    #[serde(rename = "interval_ms", with = "duration_ms")]
+    pub interval: Duration,
+}
+
+impl Default for MetricsEventConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl MetricsEventConfig {
+    pub fn apply_env(&mut self) {
+        self.enabled = env_or("QKD_BACKEND_METRICS_EVENT", self.enabled);
+        self.interval = Duration::from_millis(env_or(
+            "QKD_BACKEND_METRICS_INTERVAL_MS",
+            self.interval.as_millis() as u64,
+        ));
+    }
+}
+
+/// Run `tick` every `interval` until `cancel` fires, the first tick one interval from now.
+/// A slow tick delays the next one rather than causing a burst to catch up.
+pub async fn run_every<F: Future<Output = ()>>(
+    interval: Duration,
+    cancel: &CancellationToken,
+    mut tick: impl FnMut() -> F,
+) {
+    let interval = interval.max(MIN_METRICS_EVENT_INTERVAL);
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tick() => {}
+        }
+    }
+}
+
+/// Milestones of the current backend launch, used to see where startup time goes
+#[derive(Clone, Default)]
+pub struct StartupMetrics {
+    pub spawned_at: Option<Instant>,
+    pub first_stdout_at: Option<Instant>,
+    pub ready_at: Option<Instant>,
+    pub first_http_ok_at: Option<Instant>,
+    // Which detection method marked the backend ready first, "log" or "http"
+    pub ready_method: Option<&'static str>,
+    // How long the post-readiness warmup took, `None` until the backend has been warmed up
+    pub warmup: Option<Duration>,
+}
+
+/// Milliseconds from the spawn to each milestone, `None` for milestones not reached yet
+#[derive(Serialize)]
+pub struct StartupMetricsReport {
+    pub first_stdout_ms: Option<u64>,
+    pub ready_ms: Option<u64>,
+    pub first_http_ok_ms: Option<u64>,
+    pub ready_method: Option<&'static str>,
+    pub warmup_ms: Option<u64>,
+}
+
+impl StartupMetrics {
+    /// Start timing a new launch
+    pub fn begin(at: Instant) -> Self {
+        Self {
+            spawned_at: Some(at),
+            ..Self::default()
+        }
+    }
+
+    pub fn record_first_stdout(&mut self, at: Instant) {
+        self.first_stdout_at.get_or_insert(at);
+    }
+
+    pub fn record_ready(&mut self, at: Instant, method: &'static str) {
+        if self.ready_at.is_none() {
+            self.ready_at = Some(at);
+            self.ready_method = Some(method);
+        }
+    }
+
+    pub fn record_http_ok(&mut self, at: Instant) {
+        self.first_http_ok_at.get_or_insert(at);
+    }
+
+    pub fn record_warmup(&mut self, took: Duration) {
+        self.warmup.get_or_insert(took);
+    }
+
+    pub fn report(&self) -> StartupMetricsReport {
+        let since_spawn = |at: Option<Instant>| match (self.spawned_at, at) {
+            (Some(spawned), Some(at)) => Some(at.saturating_duration_since(spawned).as_millis() as u64),
+            _ => None,
+        };
+        StartupMetricsReport {
+            first_stdout_ms: since_spawn(self.first_stdout_at),
+            ready_ms: since_spawn(self.ready_at),
+            first_http_ok_ms: since_spawn(self.first_http_ok_at),
+            ready_method: self.ready_method,
+            warmup_ms: self.warmup.map(|took| took.as_millis() as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn milestones_are_reported_from_the_spawn() {
+        let spawned = Instant::now();
+        let at = |ms| spawned + Duration::from_millis(ms);
+        let mut metrics = StartupMetrics::begin(spawned);
+        metrics.record_first_stdout(at(120));
+        metrics.record_http_ok(at(900));
+        metrics.record_ready(at(850), "log");
+        metrics.record_warmup(Duration::from_millis(300));
+        // Only the first of each milestone counts
+        metrics.record_first_stdout(at(400));
+        metrics.record_ready(at(900), "http");
+        metrics.record_http_ok(at(1500));
+        metrics.record_warmup(Duration::from_millis(50));
+
+        let report = metrics.report();
+        assert_eq!(report.first_stdout_ms, Some(120));
+        assert_eq!(report.ready_ms, Some(850));
+        assert_eq!(report.first_http_ok_ms, Some(900));
+        assert_eq!(report.ready_method, Some("log"));
+        assert_eq!(report.warmup_ms, Some(300));
+
+        // Nothing is reported before a spawn, nor for milestones not reached yet
+        let mut unspawned = StartupMetrics::default();
+        unspawned.record_first_stdout(at(10));
+        assert_eq!(unspawned.report().first_stdout_ms, None);
+        let pending = StartupMetrics::begin(spawned).report();
+        assert!(pending.ready_ms.is_none() && pending.ready_method.is_none() && pending.warmup_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn ticks_at_the_interval_until_cancelled() {
+        let cancel = CancellationToken::new();
+        let ticks = AtomicU32::new(0);
+        let counter = &ticks;
+        let run = run_every(Duration::from_millis(250), &cancel, move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let stop = async {
+            tokio::time::sleep(Duration::from_millis(1100)).await;
+            cancel.cancel();
+        };
+        // Returning at all shows the task stops once cancelled
+        tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(run, stop) })
+            .await
+            .unwrap();
+
+        // Ticks at 250, 500, 750 and 1000ms, with some slack for a busy machine
+        let counted = ticks.load(Ordering::SeqCst);
+        assert!((3..=4).contains(&counted), "{} ticks", counted);
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), counted);
+    }
+}
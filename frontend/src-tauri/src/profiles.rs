@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::config::BackendConfig;
+use crate::{AppHandle, LOG_TARGET};
+
+// File in the app config directory holding the named profiles
+const PROFILES_FILE_NAME: &str = "backend-profiles.json";
+
+/// Named backend configs to switch between, e.g. a local BB84 setup and a remote lab machine
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    // Profile the running config was last switched to, `None` until `switch_profile` is used
+    pub active: Option<String>,
+    pub profiles: BTreeMap<String, BackendConfig>,
+}
+
+impl Profiles {
+    /// Load the saved profiles, starting over with none if the file is missing or corrupt
+    pub fn load(path: &Path) -> Self {
+        let Ok(raw) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&raw).unwrap_or_else(|e| {
+            log::warn!(target: LOG_TARGET, "Ignoring corrupt backend profiles {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Write the profiles to disk, creating the config directory if needed
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize backend profiles: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Add or replace the profile `name`
+    pub fn insert(&mut self, name: &str, config: BackendConfig) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        config.validate()?;
+        self.profiles.insert(name.to_string(), config);
+        Ok(())
+    }
+
+    /// Remove the profile `name`, forgetting it was active. False if there was no such profile.
+    pub fn remove(&mut self, name: &str) -> bool {
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.profiles.remove(name).is_some()
+    }
+
+    /// Make `name` the active profile, returning the config to run under it
+    pub fn activate(&mut self, name: &str) -> Result<BackendConfig, String> {
+        let config = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No backend profile named '{}'", name))?;
+        self.active = Some(name.to_string());
+        Ok(config)
+    }
+}
+
+/// Location of the saved profiles in the app config directory
+pub fn profiles_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(PROFILES_FILE_NAME))
+        .map_err(|e| format!("Failed to resolve app config directory: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackendMode;
+
+    fn remote(port: u16) -> BackendConfig {
+        BackendConfig {
+            mode: BackendMode::Remote,
+            host: "10.0.0.7".into(),
+            port,
+            ..BackendConfig::default()
+        }
+    }
+
+    #[test]
+    fn profiles_survive_a_round_trip() {
+        let path = std::env::temp_dir().join(format!("qkd-lab-profiles-{}.json", std::process::id()));
+        let mut profiles = Profiles::default();
+        profiles.insert("local", BackendConfig::default()).unwrap();
+        profiles.insert("lab", remote(9000)).unwrap();
+        profiles.activate("lab").unwrap();
+        profiles.save(&path).unwrap();
+
+        let loaded = Profiles::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.names(), ["lab", "local"]);
+        assert_eq!(loaded.active.as_deref(), Some("lab"));
+        assert_eq!(loaded.profiles["lab"].port, 9000);
+        assert!(loaded.profiles["lab"].mode == BackendMode::Remote);
+    }
+
+    #[test]
+    fn missing_or_corrupt_files_load_empty() {
+        let path = std::env::temp_dir().join("qkd-lab-no-such-profiles.json");
+        assert!(Profiles::load(&path).profiles.is_empty());
+
+        let corrupt = std::env::temp_dir().join(format!("qkd-lab-corrupt-profiles-{}.json", std::process::id()));
+        std::fs::write(&corrupt, "{ not json").unwrap();
+        let loaded = Profiles::load(&corrupt);
+        std::fs::remove_file(&corrupt).unwrap();
+        assert!(loaded.profiles.is_empty() && loaded.active.is_none());
+    }
+
+    #[test]
+    fn switching_picks_the_named_config() {
+        let mut profiles = Profiles::default();
+        profiles.insert("local", BackendConfig::default()).unwrap();
+        profiles.insert("lab", remote(9000)).unwrap();
+
+        assert_eq!(profiles.activate("lab").unwrap().port, 9000);
+        assert_eq!(profiles.activate("local").unwrap().port, BackendConfig::default().port);
+        assert_eq!(profiles.active.as_deref(), Some("local"));
+
+        // An unknown profile leaves the active one alone
+        assert!(profiles.activate("missing").is_err());
+        assert_eq!(profiles.active.as_deref(), Some("local"));
+
+        assert!(profiles.remove("local"));
+        assert!(profiles.active.is_none());
+        assert!(profiles.insert("  ", BackendConfig::default()).is_err());
+    }
+}
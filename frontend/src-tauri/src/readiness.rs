@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+/// Line printed by the backend once its server socket is accepting connections
+pub const READY_MARKER: &str = "QKD_BACKEND_READY";
+
+/// Markers used unless the config names its own, see `BackendConfig::ready_markers`
+pub fn default_ready_markers() -> Vec<String> {
+    vec![READY_MARKER.to_string()]
+}
+
+/// A readiness line, e.g. `QKD_BACKEND_READY port=8000`
+pub struct ReadyMarker {
+    // Which of the configured markers the line contained
+    pub marker: String,
+    pub port: Option<u16>,
+}
+
+/// Check a line of backend stdout for any of `markers`, matched case-insensitively anywhere in
+/// the line as long as it isn't part of a longer word, so `QKD_BACKEND_READY_TIMEOUT=30` in an
+/// environment dump doesn't count. `key=value` fields after the marker may report the port
+/// actually bound.
+pub fn parse_ready_marker(line: &str, markers: &[String]) -> Option<ReadyMarker> {
+    // ASCII lowercasing keeps byte offsets, so positions found here index `line` too
+    let lowered = line.to_ascii_lowercase();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let (marker, end) = markers
+        .iter()
+        .filter(|marker| !marker.is_empty())
+        .find_map(|marker| {
+            let (start, _) = lowered.match_indices(&marker.to_ascii_lowercase()).find(|(start, _)| {
+                let before = line[..*start].chars().next_back();
+                let after = line[start + marker.len()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })?;
+            Some((marker, start + marker.len()))
+        })?;
+
+    let port = line[end..]
+        .split_whitespace()
+        .filter_map(|field| field.split_once('='))
+        .find(|(key, _)| *key == "port")
+        .and_then(|(_, value)| value.parse().ok());
+    Some(ReadyMarker {
+        marker: marker.clone(),
+        port,
+    })
+}
+
+/// Which signals must be seen before the backend counts as ready
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessPolicy {
+    /// Whichever of the log marker or a healthy HTTP response comes first
+    #[default]
+    LogOrHttp,
+    /// Only a healthy HTTP response, the log marker is ignored
+    HttpOnly,
+    /// Both the log marker and a healthy HTTP response, for a fully initialized process
+    LogAndHttp,
+}
+
+impl std::str::FromStr for ReadinessPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "log_or_http" => Ok(Self::LogOrHttp),
+            "http_only" => Ok(Self::HttpOnly),
+            "log_and_http" => Ok(Self::LogAndHttp),
+            other => Err(format!("Unknown readiness policy '{}'", other)),
+        }
+    }
+}
+
+/// Readiness signals seen so far for the current launch, shared by the log monitor and health task
+#[derive(Clone, Copy, Default)]
+pub struct ReadinessSignals {
+    pub log: bool,
+    pub http: bool,
+}
+
+impl ReadinessSignals {
+    /// Note a signal from the given detection method, "log" or "http"
+    pub fn record(&mut self, method: &str) {
+        match method {
+            "log" => self.log = true,
+            _ => self.http = true,
+        }
+    }
+
+    pub fn satisfies(&self, policy: ReadinessPolicy) -> bool {
+        match policy {
+            ReadinessPolicy::LogOrHttp => self.log || self.http,
+            ReadinessPolicy::HttpOnly => self.http,
+            ReadinessPolicy::LogAndHttp => self.log && self.http,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_marker_reports_the_bound_port() {
+        let markers = default_ready_markers();
+        let marker = parse_ready_marker("QKD_BACKEND_READY port=8013", &markers).unwrap();
+        assert_eq!((marker.marker.as_str(), marker.port), (READY_MARKER, Some(8013)));
+        assert_eq!(parse_ready_marker("qkd_backend_ready", &markers).unwrap().port, None);
+        assert!(parse_ready_marker("INFO:     Uvicorn running on http://127.0.0.1:8000", &markers).is_none());
+    }
+
+    #[test]
+    fn custom_markers_match_case_insensitively() {
+        let markers = vec!["Uvicorn running on".to_string(), "Listening at".to_string()];
+        let marker = parse_ready_marker("INFO:     uvicorn RUNNING ON http://127.0.0.1:8000", &markers).unwrap();
+        assert_eq!(marker.marker, "Uvicorn running on");
+        assert_eq!(marker.port, None);
+        assert_eq!(
+            parse_ready_marker("[gunicorn] Listening at: port=9001", &markers).map(|m| m.port),
+            Some(Some(9001))
+        );
+
+        // Neither the built-in marker nor unrelated output count once markers are configured
+        assert!(parse_ready_marker("QKD_BACKEND_READY port=8000", &markers).is_none());
+        assert!(parse_ready_marker("INFO:     Waiting for application startup.", &markers).is_none());
+        assert!(parse_ready_marker("anything", &[String::new()]).is_none());
+    }
+
+    #[test]
+    fn each_policy_waits_for_its_signals() {
+        // Which of the startup events leave the backend ready, in the order they arrive
+        let cases = [
+            (ReadinessPolicy::LogOrHttp, ["log", "http"], [true, true]),
+            (ReadinessPolicy::LogOrHttp, ["http", "log"], [true, true]),
+            (ReadinessPolicy::HttpOnly, ["log", "http"], [false, true]),
+            (ReadinessPolicy::HttpOnly, ["http", "log"], [true, true]),
+            (ReadinessPolicy::LogAndHttp, ["log", "http"], [false, true]),
+            (ReadinessPolicy::LogAndHttp, ["http", "log"], [false, true]),
+        ];
+        for (policy, events, expected) in cases {
+            let mut signals = ReadinessSignals::default();
+            assert!(!signals.satisfies(policy));
+            let ready: Vec<_> = events
+                .iter()
+                .map(|method| {
+                    signals.record(method);
+                    signals.satisfies(policy)
+                })
+                .collect();
+            assert_eq!(ready, expected, "{:?} after {:?}", policy, events);
+        }
+
+        // Repeats of one signal never stand in for the other
+        let mut signals = ReadinessSignals::default();
+        for _ in 0..3 {
+            signals.record("log");
+        }
+        assert!(!signals.satisfies(ReadinessPolicy::HttpOnly));
+        assert!(!signals.satisfies(ReadinessPolicy::LogAndHttp));
+    }
+
+    #[test]
+    fn policies_parse_from_config_names() {
+        for (name, policy) in [
+            ("log_or_http", ReadinessPolicy::LogOrHttp),
+            ("http-only", ReadinessPolicy::HttpOnly),
+            ("LOG_AND_HTTP", ReadinessPolicy::LogAndHttp),
+        ] {
+            assert_eq!(name.parse::<ReadinessPolicy>(), Ok(policy), "{}", name);
+        }
+        assert!("log_xor_http".parse::<ReadinessPolicy>().is_err());
+        assert_eq!(ReadinessPolicy::default(), ReadinessPolicy::LogOrHttp);
+    }
+
+    #[test]
+    fn near_misses_are_not_readiness() {
+        let markers = default_ready_markers();
+        for line in [
+            "",
+            "QKD_BACKEND_READ port=8000",
+            "QKD-BACKEND-READY port=8000",
+            "QKD BACKEND READY port=8000",
+            "QKD_BACKEND_NOT_READY port=8000",
+            "QKD_BACKEND_READY_TIMEOUT=30",
+            "export XQKD_BACKEND_READY=1",
+            "INFO:     Application startup complete.",
+        ] {
+            assert!(parse_ready_marker(line, &markers).is_none(), "{}", line);
+        }
+
+        // The marker itself still counts behind a logger prefix, with or without a usable port
+        let port = |line| parse_ready_marker(line, &markers).map(|marker| marker.port);
+        assert_eq!(port("INFO:     QKD_BACKEND_READY port=8000"), Some(Some(8000)));
+        assert_eq!(port("[backend] QKD_BACKEND_READY: port=8000"), Some(Some(8000)));
+        assert_eq!(port("QKD_BACKEND_READY port=80000"), Some(None));
+        assert_eq!(port("QKD_BACKEND_READY port=http"), Some(None));
+        assert_eq!(port("QKD_BACKEND_READY sport=9000"), Some(None));
+        assert_eq!(port("QKD_BACKEND_READY port = 8000"), Some(None));
+    }
+}
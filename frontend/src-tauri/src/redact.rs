@@ -0,0 +1,138 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Text that replaces anything masked in backend output
+const REDACTED: &str = "<redacted>";
+
+/// Hex blobs long enough to be keys or hashes of them (raw bit strings included), and printed
+/// bit lists such as `[0, 1, 1, ...]` of 32 bits or more
+const DEFAULT_PATTERNS: [&str; 2] = [
+    r"\b[0-9a-fA-F]{32,}\b",
+    r"\[(?:\s*[01]\s*,){31,}\s*[01]\s*\]",
+];
+
+/// Masking applied to backend output before it is buffered, logged, written to disk or emitted
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    // Turn off for trusted development setups that need the raw output
+    pub enabled: bool,
+    // Regular expressions whose matches are masked
+    pub patterns: Vec<String>,
+    // Lines containing this are masked entirely, for backends that flag their own secrets
+    pub sensitive_marker: String,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            patterns: DEFAULT_PATTERNS.iter().map(|pattern| pattern.to_string()).collect(),
+            sensitive_marker: "[SENSITIVE]".to_string(),
+        }
+    }
+}
+
+/// `RedactionConfig` with its patterns compiled
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    sensitive_marker: Option<String>,
+}
+
+impl Redactor {
+    /// Compile the configured patterns, a disabled config yields a redactor that masks nothing
+    pub fn new(config: &RedactionConfig) -> Result<Self, String> {
+        if !config.enabled {
+            return Ok(Self {
+                patterns: Vec::new(),
+                sensitive_marker: None,
+            });
+        }
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| format!("Invalid redaction pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            patterns,
+            sensitive_marker: Some(config.sensitive_marker.clone()).filter(|marker| !marker.is_empty()),
+        })
+    }
+
+    /// Mask `line`, borrowing it unchanged when nothing matched
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if let Some(marker) = &self.sensitive_marker {
+            if line.contains(marker.as_str()) {
+                return format!("{} {}", marker, REDACTED).into();
+            }
+        }
+        let mut line = Cow::Borrowed(line);
+        for pattern in &self.patterns {
+            let masked = match pattern.replace_all(&line, REDACTED) {
+                Cow::Owned(masked) => Some(masked),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(masked) = masked {
+                line = Cow::Owned(masked);
+            }
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::new(&RedactionConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn key_material_is_masked() {
+        let redactor = redactor();
+        assert_eq!(
+            redactor.redact("final key 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"),
+            "final key <redacted>"
+        );
+        assert_eq!(redactor.redact(&format!("sifted {}", "01".repeat(40))), "sifted <redacted>");
+        let bits = ["1"; 40].join(", ");
+        assert_eq!(redactor.redact(&format!("bob bits [{}] done", bits)), "bob bits <redacted> done");
+        assert_eq!(redactor.redact("[SENSITIVE] alice=0110"), "[SENSITIVE] <redacted>");
+    }
+
+    #[test]
+    fn ordinary_lines_pass_through() {
+        let redactor = redactor();
+        for line in [
+            "INFO:     Uvicorn running on http://127.0.0.1:8000",
+            "QKD_BACKEND_READY port=8000",
+            "QBER 0.0312 over 10000 photons, bits [0, 1, 1]",
+            "commit 9f86d08",
+        ] {
+            assert!(matches!(redactor.redact(line), Cow::Borrowed(_)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn disabled_redaction_masks_nothing() {
+        let config = RedactionConfig {
+            enabled: false,
+            ..RedactionConfig::default()
+        };
+        let line = "[SENSITIVE] key 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b";
+        assert_eq!(Redactor::new(&config).unwrap().redact(line), line);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let config = RedactionConfig {
+            patterns: vec!["[0-9".to_string()],
+            ..RedactionConfig::default()
+        };
+        assert!(Redactor::new(&config).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::config::{duration_ms, env_or};
+
+/// Settings for sampling the embedded backend's memory and CPU use
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceConfig {
+    pub enabled: bool,
+    #[serde(rename = "interval_ms", with = "duration_ms")]
+    pub interval: Duration,
+    // Samples kept for `get_backend_resource_usage`, the oldest dropped first
+    pub history: usize,
+    // Emit `backend-resource-warning` once RSS goes above this many MiB, 0 to never warn
+    pub rss_warning_mb: u64,
+}
+
+impl Default for ResourceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(2),
+            history: 150,
+            rss_warning_mb: 2048,
+        }
+    }
+}
+
+impl ResourceConfig {
+    pub fn apply_env(&mut self) {
+        self.enabled = env_or("QKD_BACKEND_RESOURCES", self.enabled);
+        self.interval = Duration::from_millis(env_or(
+            "QKD_BACKEND_RESOURCES_INTERVAL_MS",
+            self.interval.as_millis() as u64,
+        ));
+        self.rss_warning_mb = env_or("QKD_BACKEND_RSS_WARNING_MB", self.rss_warning_mb);
+    }
+
+    /// The warning threshold in bytes, `None` when warnings are off
+    pub fn rss_warning_bytes(&self) -> Option<u64> {
+        (self.rss_warning_mb > 0).then(|| self.rss_warning_mb * 1024 * 1024)
+    }
+}
+
+/// Memory and CPU use of the backend process at one point in time
+#[derive(Clone, Copy, Serialize)]
+pub struct ResourceSample {
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub rss_bytes: u64,
+    // Percent of one core since the previous sample, so above 100 for a busy multi-threaded run
+    pub cpu_percent: f32,
+}
+
+/// Latest sample and the ones before it, oldest first
+#[derive(Clone, Default, Serialize)]
+pub struct ResourceUsage {
+    pub current: Option<ResourceSample>,
+    pub recent: Vec<ResourceSample>,
+}
+
+/// Bounded history of samples for the current backend
+#[derive(Default)]
+pub struct ResourceHistory {
+    samples: VecDeque<ResourceSample>,
+    capacity: usize,
+}
+
+impl ResourceHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: ResourceSample) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn usage(&self) -> ResourceUsage {
+        ResourceUsage {
+            current: self.samples.back().copied(),
+            recent: self.samples.iter().copied().collect(),
+        }
+    }
+}
+
+/// Reads the resource use of one process by PID
+#[cfg(desktop)]
+pub struct ResourceSampler {
+    system: sysinfo::System,
+    pid: sysinfo::Pid,
+}
+
+#[cfg(desktop)]
+impl ResourceSampler {
+    pub fn new(pid: u32) -> Self {
+        Self {
+            system: sysinfo::System::new(),
+            pid: sysinfo::Pid::from_u32(pid),
+        }
+    }
+
+    /// Take a sample, `None` once the process is gone. CPU use needs two refreshes to measure,
+    /// so the first sample reports 0%.
+    pub fn sample(&mut self, timestamp: u64) -> Option<ResourceSample> {
+        if !self.system.refresh_process(self.pid) {
+            return None;
+        }
+        let process = self.system.process(self.pid)?;
+        Some(ResourceSample {
+            timestamp,
+            rss_bytes: process.memory(),
+            cpu_percent: process.cpu_usage(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64) -> ResourceSample {
+        ResourceSample {
+            timestamp,
+            rss_bytes: 1024,
+            cpu_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn history_keeps_the_latest_samples() {
+        let mut history = ResourceHistory::new(3);
+        assert!(history.usage().current.is_none());
+        for timestamp in 1..=5 {
+            history.push(sample(timestamp));
+        }
+        let usage = history.usage();
+        assert_eq!(usage.current.map(|sample| sample.timestamp), Some(5));
+        let timestamps: Vec<_> = usage.recent.iter().map(|sample| sample.timestamp).collect();
+        assert_eq!(timestamps, [3, 4, 5]);
+    }
+
+    #[test]
+    fn warning_threshold_can_be_disabled() {
+        let config = ResourceConfig {
+            rss_warning_mb: 0,
+            ..ResourceConfig::default()
+        };
+        assert_eq!(config.rss_warning_bytes(), None);
+        assert_eq!(ResourceConfig::default().rss_warning_bytes(), Some(2048 * 1024 * 1024));
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn sampler_handles_a_missing_process() {
+        // Far above any real PID limit
+        let mut sampler = ResourceSampler::new(u32::MAX - 7);
+        assert!(sampler.sample(0).is_none());
+        assert!(sampler.sample(1).is_none());
+
+        let mut own = ResourceSampler::new(std::process::id());
+        assert!(own.sample(0).is_some_and(|sample| sample.rss_bytes > 0));
+    }
+}
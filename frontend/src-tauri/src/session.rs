@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::api::{self, ProxyRequest};
+use crate::sync::LockExt;
+use crate::{AppHandle, BackendState, LOG_TARGET};
+
+// How long a replay waits for the backend to come back before giving up
+const REPLAY_READY_TIMEOUT: Duration = Duration::from_secs(30);
+const REPLAY_READY_POLL: Duration = Duration::from_millis(250);
+
+/// One proxied call and the status the backend answered it with
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordedStep {
+    pub request: ProxyRequest,
+    // `None` when the call never got a response
+    pub status: Option<u16>,
+}
+
+/// A recorded sequence of `backend_request` calls, saved as JSON for later replay
+#[derive(Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub recorded_at: u64,
+    pub backend_version: Option<String>,
+    pub steps: Vec<RecordedStep>,
+}
+
+impl Recording {
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize recording: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Invalid recording {}: {}", path.display(), e))
+    }
+}
+
+/// Outcome of replaying one step
+#[derive(Serialize)]
+pub struct StepResult {
+    pub method: String,
+    pub path: String,
+    pub expected_status: Option<u16>,
+    pub status: Option<u16>,
+    // The backend answered with the same status as when the step was recorded
+    pub matched: bool,
+    pub error: Option<String>,
+}
+
+/// Per-step results of a replay plus the totals
+#[derive(Serialize)]
+pub struct ReplaySummary {
+    pub matched: usize,
+    pub failed: usize,
+    pub steps: Vec<StepResult>,
+}
+
+/// Re-issue every recorded step in order against the current backend.
+/// The backend may have been restarted since recording, so wait for it to be ready first and
+/// look up its address before each step in case it moves mid-replay.
+pub async fn replay(app: &AppHandle, recording: Recording) -> Result<ReplaySummary, String> {
+    let state = app.state::<BackendState>();
+    let waited = tokio::time::timeout(REPLAY_READY_TIMEOUT, async {
+        while !state.is_ready() {
+            tokio::time::sleep(REPLAY_READY_POLL).await;
+        }
+    });
+    if waited.await.is_err() {
+        return Err(format!(
+            "Backend was not ready within {}s, nothing replayed",
+            REPLAY_READY_TIMEOUT.as_secs()
+        ));
+    }
+
+    let mut steps = Vec::with_capacity(recording.steps.len());
+    for step in recording.steps {
+        let host = state.config().host;
+        let port = *state.port.lock_recover();
+        let (method, path) = (step.request.method.clone(), step.request.path.clone());
+        let (status, error) = match api::proxy(&host, port, step.request).await {
+            Ok(resp) => (Some(resp.status), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let matched = status.is_some() && status == step.status;
+        if !matched {
+            log::warn!(
+                target: LOG_TARGET,
+                "Replayed {} {} returned {:?}, recorded {:?}",
+                method, path, status, step.status
+            );
+        }
+        steps.push(StepResult {
+            method,
+            path,
+            expected_status: step.status,
+            status,
+            matched,
+            error,
+        });
+    }
+
+    let matched = steps.iter().filter(|step| step.matched).count();
+    Ok(ReplaySummary {
+        matched,
+        failed: steps.len() - matched,
+        steps,
+    })
+}
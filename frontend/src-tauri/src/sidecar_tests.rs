@@ -0,0 +1,840 @@
+//! End-to-end checks of the spawn -> monitor -> health pipeline against a mock sidecar.
+//!
+//! The mock is this test binary re-run with `QKD_MOCK_BACKEND` set, which turns the
+//! `mock_backend` test into a tiny backend: it prints the readiness marker and answers
+//! `/health` like the Python backend does. Its behavior comes from the environment:
+//! `QKD_MOCK_BACKEND` is `ready`, `reloadable` (ready, with `/reload`), `documented` (ready,
+//! with `/openapi.json` and `/protocols`), `slow` (ready, taking `MOCK_LATENCY` over every
+//! answer), `crash` or `never_ready`, `QKD_MOCK_DELAY_MS` delays startup and `QKD_MOCK_PORT`
+//! is the port to bind, chosen by the test like the app would.
+//! Like the real backend it identifies itself with the `QKD_INSTANCE_ID` it was given.
+//!
+//! The app side is exercised too: `install_mock_sidecar` puts the mock where the shell plugin
+//! looks for the sidecar, so `spawn_backend` can launch it on a mock app.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Stdio;
+use std::time::Duration;
+
+#[cfg(all(desktop, unix))]
+use tauri::{Listener, Manager};
+use tokio::io::AsyncBufReadExt;
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+use crate::api::{self, BackendApiError, RunParams};
+#[cfg(all(desktop, unix))]
+use crate::config::BackendConfig;
+use crate::config::env_or;
+use crate::exit::ExitKind;
+use crate::health::{
+    await_ready, check_connection, perform_health_check, ConnectionCheck, HealthCheckConfig, HealthError,
+    ProbeTarget, ProcessAlive, INSTANCE_HEADER,
+};
+use crate::logs::EarlyStderr;
+use crate::readiness::{default_ready_markers, parse_ready_marker, ReadinessPolicy, ReadinessSignals, READY_MARKER};
+#[cfg(all(desktop, unix))]
+use crate::{spawn_backend, stop_backend, AppHandle, BackendState, RestartPolicy};
+
+const MOCK_ENV: &str = "QKD_MOCK_BACKEND";
+// Instance id every mock is launched with
+const MOCK_INSTANCE: &str = "4242-mock";
+// How long the `slow` scenario takes to answer a request
+const MOCK_LATENCY: Duration = Duration::from_millis(20);
+// Exit code of the `crash` scenario
+const MOCK_CRASH_CODE: i32 = 3;
+// Size of the mock's `final.key` result file, big enough to arrive in several chunks
+const MOCK_KEY_SIZE: usize = 256 * 1024;
+// Generous bound on how long any scenario may take to show its hand
+const SCENARIO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The mock backend itself, a no-op unless the binary was started by `spawn_mock`
+#[test]
+fn mock_backend() {
+    let Ok(mode) = std::env::var(MOCK_ENV) else {
+        return;
+    };
+    std::thread::sleep(Duration::from_millis(env_or("QKD_MOCK_DELAY_MS", 0)));
+    if mode == "crash" {
+        eprintln!("Traceback (most recent call last):");
+        eprintln!("RuntimeError: mock backend crashed on startup");
+        std::process::exit(MOCK_CRASH_CODE);
+    }
+
+    let port: u16 = env_or("QKD_MOCK_PORT", 0);
+    let listener = TcpListener::bind(("127.0.0.1", port)).expect("mock backend failed to bind");
+    let ready = mode != "never_ready";
+    let reloadable = mode == "reloadable";
+    let documented = mode == "documented";
+    let latency = if mode == "slow" { MOCK_LATENCY } else { Duration::ZERO };
+    if ready {
+        println!("{} port={}", READY_MARKER, listener.local_addr().unwrap().port());
+    }
+    for stream in listener.incoming().flatten() {
+        std::thread::sleep(latency);
+        serve(stream, ready, reloadable, documented);
+    }
+}
+
+/// Read one HTTP request off `stream`, returning its request line
+fn read_request(stream: &TcpStream) -> Option<String> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    // Drain the headers and any body, as sent to `/reload` and `/runs`
+    let mut line = String::new();
+    let mut content_length = 0;
+    while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+        line.clear();
+    }
+    let _ = reader.read_exact(&mut vec![0; content_length]);
+    Some(request_line)
+}
+
+/// Answer one HTTP request the way the backend would
+fn serve(mut stream: TcpStream, ready: bool, reloadable: bool, documented: bool) {
+    let Some(request_line) = read_request(&stream) else {
+        return;
+    };
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let key;
+    let (status, body) = match path {
+        "/health" if ready => ("200 OK", r#"{"status":"ok","qkd_engine":true}"#),
+        "/health" => ("200 OK", r#"{"status":"starting","qkd_engine":false}"#),
+        "/docs" => ("200 OK", "<html></html>"),
+        "/runs" if ready => ("200 OK", r#"{"session_id":"5f1c0a9e"}"#),
+        "/runs/5f1c0a9e/artifacts" if ready => (
+            "200 OK",
+            r#"{"artifacts":[
+                {"name":"qber.png","size":48213,"type":"image/png","path":"/results/5f1c0a9e/qber.png"},
+                {"name":"sweep.csv","size":1920,"type":"text/csv","path":"/results/5f1c0a9e/sweep.csv"},
+                {"name":"final.key","size":262144,"type":"application/octet-stream","path":"/results/5f1c0a9e/final.key"}
+            ]}"#,
+        ),
+        "/results/5f1c0a9e/final.key" if ready => {
+            key = "0123456789abcdef".repeat(MOCK_KEY_SIZE / 16);
+            ("200 OK", key.as_str())
+        }
+        "/runs/0b7d4e21/artifacts" if ready => ("200 OK", r#"{"artifacts":[]}"#),
+        "/reload" if reloadable => ("200 OK", r#"{"reloaded":["QKD_LOG_LEVEL"]}"#),
+        "/openapi.json" if documented => (
+            "200 OK",
+            r#"{"openapi":"3.1.0","info":{"title":"QKD Lab"},"paths":{"/health":{},"/simulate":{}}}"#,
+        ),
+        "/protocols" if documented => (
+            "200 OK",
+            r#"[
+                {"name":"bb84","display_name":"BB84","description":"Bennett-Brassard 1984","parameters":[
+                    {"name":"photons","type":"int","description":"Photons sent","default":1000,"required":false},
+                    {"name":"distance","type":"float","description":null,"default":null,"required":true}
+                ]},
+                {"name":"e91","display_name":null,"description":null}
+            ]"#,
+        ),
+        _ => ("404 Not Found", r#"{"detail":"Not Found"}"#),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        INSTANCE_HEADER,
+        env_or("QKD_INSTANCE_ID", String::new()),
+        body.len(),
+        body
+    );
+}
+
+/// Port of an in-process listener answering every request with `status` and `body`, for
+/// answers the mock backend never gives
+fn answer_with(status: &'static str, body: &'static str) -> u16 {
+    answer_on(TcpListener::bind(("127.0.0.1", 0)).unwrap(), status, body)
+}
+
+/// Like `answer_with`, on a listener the caller bound
+fn answer_on(listener: TcpListener, status: &'static str, body: &'static str) -> u16 {
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if read_request(&stream).is_some() {
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        }
+    });
+    port
+}
+
+/// A port nothing is listening on right now
+fn free_port() -> u16 {
+    TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .unwrap()
+}
+
+/// Start the mock backend in `mode`, with stdout and stderr piped like the sidecar's
+fn spawn_mock(mode: &str, port: u16, delay_ms: u64) -> Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["sidecar_tests::mock_backend", "--exact", "--nocapture", "--test-threads=1"])
+        .env(MOCK_ENV, mode)
+        .env("QKD_MOCK_PORT", port.to_string())
+        .env("QKD_MOCK_DELAY_MS", delay_ms.to_string())
+        .env("QKD_INSTANCE_ID", MOCK_INSTANCE)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start the mock backend")
+}
+
+/// Watch stdout for the readiness marker like the monitor task, returning the port it reports
+async fn wait_for_marker(child: &mut Child) -> Option<u16> {
+    let mut lines = tokio::io::BufReader::new(child.stdout.take()?).lines();
+    let watch = async {
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(marker) = parse_ready_marker(&line, &default_ready_markers()) {
+                return marker.port;
+            }
+        }
+        None
+    };
+    tokio::time::timeout(SCENARIO_TIMEOUT, watch).await.ok().flatten()
+}
+
+fn health_config() -> HealthCheckConfig {
+    HealthCheckConfig {
+        request_timeout: Duration::from_millis(500),
+        ..HealthCheckConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn ready_backend_satisfies_every_policy() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    let mut signals = ReadinessSignals::default();
+
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+    signals.record("log");
+    assert!(signals.satisfies(ReadinessPolicy::LogOrHttp));
+    assert!(!signals.satisfies(ReadinessPolicy::LogAndHttp));
+
+    let health = perform_health_check(&health_config(), "127.0.0.1", port).await.unwrap();
+    assert!(health.ready);
+    assert_eq!(health.family, "ipv4");
+    signals.record("http");
+    assert!(signals.satisfies(ReadinessPolicy::HttpOnly));
+    assert!(signals.satisfies(ReadinessPolicy::LogAndHttp));
+}
+
+#[tokio::test]
+async fn each_probe_outcome_is_told_apart() {
+    let config = health_config();
+    let probe = |port| perform_health_check(&config, "127.0.0.1", port);
+
+    // Nothing listening, the TCP pre-check answers without any HTTP
+    assert_eq!(probe(free_port()).await.err(), Some(HealthError::ConnectionRefused));
+    // Listening, but every endpoint fails
+    let port = answer_with("500 Internal Server Error", r#"{"detail":"engine failed to load"}"#);
+    assert_eq!(probe(port).await.err(), Some(HealthError::BadStatus(500)));
+    // Answering, but not like our backend
+    let port = answer_with("200 OK", "<html></html>");
+    assert!(matches!(probe(port).await, Err(HealthError::InvalidBody(_))));
+    // Answering, ready or not
+    let port = answer_with("200 OK", r#"{"status":"starting","qkd_engine":false}"#);
+    assert!(!probe(port).await.unwrap().ready);
+    let port = answer_with("200 OK", r#"{"status":"ok"}"#);
+    let health = probe(port).await.unwrap();
+    assert!(health.ready);
+    assert_eq!(health.family, "ipv4");
+    // Answering for somebody else
+    let launched = HealthCheckConfig {
+        instance_id: Some(MOCK_INSTANCE.to_string()),
+        ..health_config()
+    };
+    assert_eq!(
+        perform_health_check(&launched, "127.0.0.1", port).await.err(),
+        Some(HealthError::ForeignInstance(None))
+    );
+}
+
+#[tokio::test]
+async fn backend_is_found_on_either_loopback_family() {
+    let config = health_config();
+    let port = answer_with("200 OK", r#"{"status":"ok"}"#);
+    assert_eq!(perform_health_check(&config, "::1", port).await.unwrap().family, "ipv4");
+    assert_eq!(perform_health_check(&config, "localhost", port).await.unwrap().family, "ipv4");
+
+    // A backend bound to IPv6 only is still found when IPv4 was configured
+    let Ok(listener) = TcpListener::bind(("::1", 0)) else {
+        eprintln!("skipping IPv6 half, ::1 is not available here");
+        return;
+    };
+    let port = answer_on(listener, "200 OK", r#"{"status":"ok"}"#);
+    assert_eq!(perform_health_check(&config, "127.0.0.1", port).await.unwrap().family, "ipv6");
+    assert_eq!(perform_health_check(&config, "localhost", port).await.unwrap().family, "ipv6");
+}
+
+#[tokio::test]
+async fn delayed_backend_is_refused_until_it_binds() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 500);
+
+    let early = perform_health_check(&health_config(), "127.0.0.1", port).await;
+    assert_eq!(early.err(), Some(HealthError::ConnectionRefused));
+
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+    let health = perform_health_check(&health_config(), "127.0.0.1", port).await.unwrap();
+    assert!(health.ready);
+}
+
+#[tokio::test]
+async fn crashing_backend_exits_without_the_marker() {
+    let mut child = spawn_mock("crash", free_port(), 0);
+    let mut stderr = child.stderr.take().unwrap();
+
+    assert_eq!(wait_for_marker(&mut child).await, None);
+    let status = tokio::time::timeout(SCENARIO_TIMEOUT, child.wait()).await.unwrap().unwrap();
+    assert_eq!(status.code(), Some(MOCK_CRASH_CODE));
+    assert_eq!(ExitKind::classify(status.code(), None), ExitKind::Error);
+
+    let mut output = Vec::new();
+    tokio::io::AsyncReadExt::read_to_end(&mut stderr, &mut output).await.unwrap();
+    assert!(String::from_utf8_lossy(&output).contains("mock backend crashed"));
+}
+
+#[tokio::test]
+async fn never_ready_backend_answers_but_is_not_ready() {
+    let port = free_port();
+    let _child = spawn_mock("never_ready", port, 0);
+
+    // No marker is coming, poll until the port answers like the health task does
+    let config = health_config();
+    let health = tokio::time::timeout(SCENARIO_TIMEOUT, async {
+        loop {
+            match perform_health_check(&config, "127.0.0.1", port).await {
+                Err(HealthError::ConnectionRefused) => {
+                    tokio::time::sleep(config.initial_delay).await;
+                }
+                other => return other,
+            }
+        }
+    })
+    .await
+    .unwrap()
+    .unwrap();
+
+    // An answer that isn't ready is never recorded, so no policy is satisfied
+    assert!(!health.ready);
+    assert!(!ReadinessSignals::default().satisfies(ReadinessPolicy::LogOrHttp));
+}
+
+#[tokio::test]
+async fn reload_endpoint_is_confirmed_by_health() {
+    let port = free_port();
+    let mut child = spawn_mock("reloadable", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let env = BTreeMap::from([("QKD_LOG_LEVEL".to_string(), "debug".to_string())]);
+    api::reload_config("127.0.0.1", port, &env).await.unwrap();
+    let health = await_ready(&health_config(), "127.0.0.1", port, SCENARIO_TIMEOUT).await.unwrap();
+    assert!(health.ready);
+}
+
+#[tokio::test]
+async fn missing_reload_endpoint_falls_back() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    // `reload_backend_config` restarts the backend on exactly this error
+    let result = api::reload_config("127.0.0.1", port, &BTreeMap::new()).await;
+    assert!(matches!(result, Err(BackendApiError::NotSupported(_))));
+}
+
+#[tokio::test]
+async fn only_the_launched_instance_counts_as_ready() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let launched = HealthCheckConfig {
+        instance_id: Some(MOCK_INSTANCE.to_string()),
+        ..health_config()
+    };
+    assert!(perform_health_check(&launched, "127.0.0.1", port).await.unwrap().ready);
+
+    // The same answer is a stale backend when we launched a different instance
+    let relaunched = HealthCheckConfig {
+        instance_id: Some("4243-other".to_string()),
+        ..health_config()
+    };
+    assert_eq!(
+        perform_health_check(&relaunched, "127.0.0.1", port).await.err(),
+        Some(HealthError::ForeignInstance(Some(MOCK_INSTANCE.to_string())))
+    );
+}
+
+#[tokio::test]
+async fn openapi_schema_is_fetched() {
+    let port = free_port();
+    let mut child = spawn_mock("documented", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let schema = api::fetch_openapi_schema("127.0.0.1", port).await.unwrap();
+    assert_eq!(schema["openapi"], "3.1.0");
+    assert!(schema["paths"].get("/simulate").is_some());
+}
+
+#[tokio::test]
+async fn missing_openapi_schema_is_not_supported() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let result = api::fetch_openapi_schema("127.0.0.1", port).await;
+    assert!(matches!(result, Err(BackendApiError::NotSupported(path)) if path == "/openapi.json"));
+}
+
+#[tokio::test]
+async fn protocols_are_listed_with_their_parameters() {
+    let port = free_port();
+    let mut child = spawn_mock("documented", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let protocols = api::fetch_protocols("127.0.0.1", port).await.unwrap();
+    let names: Vec<_> = protocols.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["bb84", "e91"]);
+    let bb84 = &protocols[0];
+    assert_eq!(bb84.display_name.as_deref(), Some("BB84"));
+    let photons = &bb84.parameters[0];
+    assert_eq!((photons.kind.as_deref(), photons.required), (Some("int"), false));
+    assert_eq!(photons.default, Some(serde_json::json!(1000)));
+    assert!(bb84.parameters[1].required);
+    // Metadata is optional, a bare name is enough
+    assert!(protocols[1].parameters.is_empty() && protocols[1].description.is_none());
+}
+
+#[tokio::test]
+async fn missing_protocols_endpoint_is_not_supported() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let result = api::fetch_protocols("127.0.0.1", port).await;
+    assert!(matches!(result, Err(BackendApiError::NotSupported(path)) if path == "/protocols"));
+}
+
+#[tokio::test]
+async fn benchmark_reports_the_injected_latency() {
+    let port = free_port();
+    let mut child = spawn_mock("slow", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let report = api::benchmark("127.0.0.1", port, 20, 4).await.unwrap();
+    assert_eq!((report.requests, report.errors), (20, 0));
+    let latency_ms = MOCK_LATENCY.as_secs_f64() * 1000.0;
+    assert!(report.p50_ms >= latency_ms, "p50 {}ms", report.p50_ms);
+    assert!(report.p50_ms <= report.p95_ms && report.p95_ms <= report.p99_ms);
+    // The mock answers one request at a time, so throughput is bounded by its latency
+    assert!(report.requests_per_sec <= 1000.0 / latency_ms, "{}/s", report.requests_per_sec);
+
+    assert!(matches!(
+        api::benchmark("127.0.0.1", port, 20, 0).await,
+        Err(BackendApiError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+async fn validated_run_starts_a_session() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let mut params: RunParams =
+        serde_json::from_str(r#"{"protocol": "bb84", "photons": 10000, "distance": 50.0, "noise": 0.01}"#).unwrap();
+    params.validate(None).unwrap();
+    assert_eq!(api::start_run("127.0.0.1", port, &params).await.unwrap(), "5f1c0a9e");
+
+    // Out of range values never reach the backend
+    params.photons = 20;
+    params.distance = -1.0;
+    assert!(matches!(params.validate(None), Err(BackendApiError::InvalidParams(errors)) if errors.len() == 2));
+}
+
+#[tokio::test]
+async fn proxied_requests_pass_error_statuses_through() {
+    let request = |method: &str, path: &str| api::ProxyRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        body: Some(serde_json::json!({"protocol": "bb84", "photons": 10})),
+    };
+    let port = answer_with("200 OK", r#"{"qber":0.021,"key_rate":1350.0}"#);
+    let ok = api::proxy("127.0.0.1", port, request("post", "/simulate")).await.unwrap();
+    assert_eq!((ok.status, ok.body.unwrap()["qber"].as_f64()), (200, Some(0.021)));
+
+    // Error statuses are answers too, the frontend gets their bodies to show
+    let port = answer_with("422 Unprocessable Entity", r#"{"detail":[{"loc":["body","photons"],"msg":"too few"}]}"#);
+    let invalid = api::proxy("127.0.0.1", port, request("POST", "/simulate")).await.unwrap();
+    assert_eq!(invalid.status, 422);
+    assert_eq!(invalid.body.unwrap()["detail"][0]["msg"], "too few");
+    let port = answer_with("500 Internal Server Error", r#"{"detail":"engine failed"}"#);
+    let failed = api::proxy("127.0.0.1", port, request("GET", "/health")).await.unwrap();
+    assert_eq!((failed.status, failed.body.unwrap()["detail"].as_str()), (500, Some("engine failed")));
+    let port = answer_with("503 Service Unavailable", "");
+    let unavailable = api::proxy("127.0.0.1", port, request("GET", "/version")).await.unwrap();
+    assert_eq!((unavailable.status, unavailable.body), (503, None));
+    // ...as long as they are JSON
+    let port = answer_with("502 Bad Gateway", "<html><body>Bad Gateway</body></html>");
+    assert!(matches!(
+        api::proxy("127.0.0.1", port, request("GET", "/health")).await,
+        Err(BackendApiError::InvalidResponse(_))
+    ));
+
+    // Nothing outside the allowlist is sent at all
+    for (method, path) in [("GET", "/runs/../../etc/passwd"), ("GET", "/admin"), ("NOT A METHOD", "/simulate")] {
+        assert!(matches!(
+            api::proxy("127.0.0.1", port, request(method, path)).await,
+            Err(BackendApiError::Forbidden(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn corrected_port_redirects_the_next_probe() {
+    let wrong = free_port();
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    // Stands in for the shared config the startup loop reads
+    let config = std::sync::Mutex::new(("127.0.0.1".to_string(), wrong));
+    let mut target = ProbeTarget::new(|| config.lock().unwrap().clone());
+
+    let ((host, probed), changed) = target.next();
+    assert_eq!((probed, changed), (wrong, false));
+    let early = perform_health_check(&health_config(), &host, probed).await;
+    assert_eq!(early.err(), Some(HealthError::ConnectionRefused));
+
+    config.lock().unwrap().1 = port;
+    let ((host, probed), changed) = target.next();
+    assert_eq!((probed, changed), (port, true));
+    assert!(perform_health_check(&health_config(), &host, probed).await.unwrap().ready);
+    assert!(!target.next().1);
+}
+
+#[tokio::test]
+async fn connection_test_sorts_out_each_outcome() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let check = check_connection(health_config(), "127.0.0.1", port).await;
+    assert!(matches!(check, ConnectionCheck::Ok { ready: true, .. }), "{:?}", check);
+
+    let missing = HealthCheckConfig {
+        urls: vec!["{scheme}://{host}:{port}/missing".to_string()],
+        ..health_config()
+    };
+    let check = check_connection(missing, "127.0.0.1", port).await;
+    assert!(matches!(check, ConnectionCheck::BadStatus { status: Some(404), .. }), "{:?}", check);
+
+    let check = check_connection(health_config(), "127.0.0.1", free_port()).await;
+    assert!(matches!(check, ConnectionCheck::Unreachable { .. }), "{:?}", check);
+}
+
+#[tokio::test]
+async fn immediate_crash_is_explained_by_its_first_stderr() {
+    let mut child = spawn_mock("crash", free_port(), 0);
+    let mut lines = tokio::io::BufReader::new(child.stderr.take().unwrap()).lines();
+
+    // Read stderr like the monitor does until the process is gone
+    let mut early = EarlyStderr::new(5);
+    let capture = async {
+        while let Ok(Some(line)) = lines.next_line().await {
+            early.push(&line);
+        }
+        child.wait().await.unwrap()
+    };
+    let status = tokio::time::timeout(SCENARIO_TIMEOUT, capture).await.unwrap();
+    let exit = format!("exit code {}", status.code().unwrap());
+
+    assert_eq!(early.lines()[0], "Traceback (most recent call last):");
+    let reason = early.describe_exit(&exit);
+    assert!(reason.starts_with(&format!("Backend exited during startup ({})", exit)), "{}", reason);
+    assert!(reason.contains("RuntimeError: mock backend crashed on startup"), "{}", reason);
+}
+
+#[tokio::test]
+async fn termination_during_startup_stops_the_probes() {
+    let port = free_port();
+    let mut child = spawn_mock("crash", port, 200);
+    let alive = ProcessAlive::new();
+    let monitor = {
+        let alive = alive.clone();
+        tokio::spawn(async move {
+            let status = child.wait().await;
+            alive.mark_dead();
+            status
+        })
+    };
+
+    // Probe the port the crashed mock will never bind, racing the exit like the startup loop
+    let started = std::time::Instant::now();
+    let probes = await_ready(&health_config(), "127.0.0.1", port, Duration::from_secs(60));
+    let stopped = tokio::time::timeout(SCENARIO_TIMEOUT, async {
+        tokio::select! {
+            _ = alive.died() => true,
+            _ = probes => false,
+        }
+    });
+    assert_eq!(stopped.await.ok(), Some(true));
+    assert!(started.elapsed() < SCENARIO_TIMEOUT);
+    assert!(!alive.is_alive());
+    assert_eq!(monitor.await.unwrap().unwrap().code(), Some(MOCK_CRASH_CODE));
+}
+
+#[tokio::test]
+async fn session_artifacts_are_listed() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+
+    let artifacts = api::fetch_artifacts("127.0.0.1", port, "5f1c0a9e").await.unwrap();
+    let names: Vec<_> = artifacts.iter().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(names, ["qber.png", "sweep.csv", "final.key"]);
+    assert_eq!((artifacts[1].size, artifacts[1].kind.as_str()), (1920, "text/csv"));
+    assert_eq!(artifacts[2].path, api::result_path("5f1c0a9e", "final.key"));
+
+    // A run the backend has forgotten lists nothing, a 404 means the route is missing
+    assert!(api::fetch_artifacts("127.0.0.1", port, "0b7d4e21").await.unwrap().is_empty());
+    assert!(matches!(
+        api::fetch_artifacts("127.0.0.1", port, "9c2f7a10").await,
+        Err(BackendApiError::NotSupported(_))
+    ));
+    assert!(matches!(
+        api::fetch_artifacts("127.0.0.1", port, "../etc").await,
+        Err(BackendApiError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+async fn result_file_is_streamed_to_disk() {
+    let port = free_port();
+    let mut child = spawn_mock("ready", port, 0);
+    assert_eq!(wait_for_marker(&mut child).await, Some(port));
+    let dir = std::env::temp_dir().join(format!("qkd-lab-download-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dest = dir.join("final.key");
+    let size = MOCK_KEY_SIZE as u64;
+
+    let mut reports = Vec::new();
+    let path = api::result_path("5f1c0a9e", "final.key");
+    let written = api::download("127.0.0.1", port, &path, &dest, &CancellationToken::new(), |done, total| {
+        reports.push((done, total))
+    })
+    .await
+    .unwrap();
+    assert_eq!(written, size);
+    assert_eq!(std::fs::metadata(&dest).unwrap().len(), size);
+    assert!(std::fs::read_to_string(&dest).unwrap().starts_with("0123456789abcdef0123"));
+    assert_eq!(reports.first(), Some(&(0, Some(size))));
+    assert_eq!(reports.last(), Some(&(size, Some(size))));
+    assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    assert!(!dir.join("final.key.part").exists());
+
+    // A cancelled download leaves nothing behind, not even the partial file
+    let cancelled = dir.join("cancelled.key");
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let result = api::download("127.0.0.1", port, &path, &cancelled, &cancel, |_, _| {}).await;
+    assert!(matches!(result, Err(BackendApiError::Cancelled(_))));
+    assert!(!cancelled.exists() && !dir.join("cancelled.key.part").exists());
+
+    // Missing files and paths outside the results are refused
+    let cancel = CancellationToken::new();
+    let missing = api::result_path("5f1c0a9e", "absent.csv");
+    let missing = api::download("127.0.0.1", port, &missing, &cancelled, &cancel, |_, _| {}).await;
+    assert!(matches!(missing, Err(BackendApiError::InvalidRequest(_))));
+    let outside = api::download("127.0.0.1", port, "/runs", &cancelled, &cancel, |_, _| {}).await;
+    assert!(matches!(outside, Err(BackendApiError::Forbidden(_))));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Install the mock as sidecar next to the test binary, where the shell plugin resolves
+/// sidecars, returning its name. A script sits in between because libtest rejects the
+/// `--port` and `--seed` arguments the app passes; the port comes from `QKD_PORT` instead.
+#[cfg(all(desktop, unix))]
+fn install_mock_sidecar(mode: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let exe = std::env::current_exe().unwrap();
+    let name = format!("qkd-mock-sidecar-{}-{}", mode, std::process::id());
+    let path = exe.with_file_name(&name);
+    let script = format!(
+        "#!/bin/sh\nexport {}={} QKD_MOCK_PORT=\"$QKD_PORT\"\nexec '{}' sidecar_tests::mock_backend --exact --nocapture --test-threads=1\n",
+        MOCK_ENV,
+        mode,
+        exe.display()
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    name
+}
+
+/// Backend events a mock app emitted, as `(name, payload)` in the order emitted
+#[cfg(all(desktop, unix))]
+type Event = (&'static str, serde_json::Value);
+
+/// A mock app managing the backend state for `config`, and the events it emits
+#[cfg(all(desktop, unix))]
+fn mock_backend_app(config: BackendConfig) -> (tauri::App<crate::AppRuntime>, tokio::sync::mpsc::UnboundedReceiver<Event>) {
+    let app = tauri::test::mock_builder()
+        .plugin(tauri_plugin_shell::init())
+        .build(tauri::test::mock_context(tauri::test::noop_assets()))
+        .unwrap();
+    app.manage(BackendState::new(config));
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    for name in ["backend-state", "backend-ready", "backend-exited", "backend-failed", "backend-restarting"] {
+        let tx = tx.clone();
+        app.listen_any(name, move |event| {
+            let _ = tx.send((name, serde_json::from_str(event.payload()).unwrap_or_default()));
+        });
+    }
+    (app, rx)
+}
+
+/// Events up to and including the first `last`, failing the test if it doesn't come in time
+#[cfg(all(desktop, unix))]
+async fn events_until(rx: &mut tokio::sync::mpsc::UnboundedReceiver<Event>, last: &str) -> Vec<Event> {
+    let mut seen = Vec::new();
+    let collect = async {
+        while let Some((name, payload)) = rx.recv().await {
+            seen.push((name, payload));
+            if name == last {
+                break;
+            }
+        }
+    };
+    let arrived = tokio::time::timeout(SCENARIO_TIMEOUT, collect).await.is_ok();
+    assert!(arrived, "no {} event, saw {:?}", last, seen);
+    seen
+}
+
+/// The phases `backend-state` events went through
+#[cfg(all(desktop, unix))]
+fn phases(events: &[Event]) -> Vec<String> {
+    events
+        .iter()
+        .filter(|(name, _)| *name == "backend-state")
+        .map(|(_, payload)| payload["state"].as_str().unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Config launching the installed mock `sidecar` on a free port
+#[cfg(all(desktop, unix))]
+fn sidecar_config(sidecar: String) -> BackendConfig {
+    BackendConfig {
+        sidecar,
+        host: "127.0.0.1".to_string(),
+        port: free_port(),
+        working_dir: Some(std::env::temp_dir()),
+        ..BackendConfig::default()
+    }
+}
+
+/// Stop the backend `app` launched, as closing the window would
+#[cfg(all(desktop, unix))]
+async fn shut_down(app: &AppHandle) {
+    let app = app.clone();
+    tokio::task::spawn_blocking(move || stop_backend(&app)).await.unwrap();
+}
+
+#[cfg(all(desktop, unix))]
+#[tokio::test]
+async fn spawned_sidecar_is_reported_ready() {
+    let sidecar = install_mock_sidecar("ready");
+    let config = sidecar_config(sidecar.clone());
+    let port = config.port;
+    let (app, mut events) = mock_backend_app(config);
+    let handle = app.handle().clone();
+
+    spawn_backend(&handle).await.unwrap();
+    let seen = events_until(&mut events, "backend-ready").await;
+    assert_eq!(phases(&seen), ["spawning", "waiting_for_ready", "ready"]);
+    let (_, ready) = seen.last().unwrap();
+    assert_eq!(ready["port"], port);
+    assert!(ready["method"] == "log" || ready["method"] == "http");
+
+    let state = handle.state::<BackendState>();
+    assert!(state.is_ready());
+    assert!(state.pid.lock_recover().is_some());
+    assert_eq!(state.target(), ("127.0.0.1".to_string(), port));
+
+    shut_down(&handle).await;
+    assert!(!state.is_ready());
+    assert!(state.take_child().is_none());
+    let _ = std::fs::remove_file(std::env::current_exe().unwrap().with_file_name(sidecar));
+}
+
+#[cfg(all(desktop, unix))]
+#[tokio::test]
+async fn crashing_sidecar_is_reported_failed() {
+    let sidecar = install_mock_sidecar("crash");
+    let (app, mut events) = mock_backend_app(sidecar_config(sidecar.clone()));
+    let handle = app.handle().clone();
+    let state = handle.state::<BackendState>();
+    state.restart_policy.lock_recover().enabled = false;
+
+    spawn_backend(&handle).await.unwrap();
+    let seen = events_until(&mut events, "backend-failed").await;
+    assert_eq!(phases(&seen), ["spawning", "waiting_for_ready", "failed"]);
+    assert!(!seen.iter().any(|(name, _)| *name == "backend-ready"));
+    let (_, exited) = seen.iter().find(|(name, _)| *name == "backend-exited").unwrap();
+    assert_eq!(exited["exit_code"], MOCK_CRASH_CODE);
+    let (_, failed) = seen.last().unwrap();
+    assert_eq!(failed["stderr"][1], "RuntimeError: mock backend crashed on startup");
+
+    assert!(!state.is_ready());
+    let last_error = state.last_error.lock_recover().clone().unwrap();
+    assert!(last_error.message.contains("RuntimeError: mock backend crashed on startup"));
+    shut_down(&handle).await;
+    let _ = std::fs::remove_file(std::env::current_exe().unwrap().with_file_name(sidecar));
+}
+
+#[cfg(all(desktop, unix))]
+#[tokio::test]
+async fn first_crash_reports_its_stderr_with_the_restart() {
+    let sidecar = install_mock_sidecar("crash");
+    let (app, mut events) = mock_backend_app(sidecar_config(sidecar.clone()));
+    let handle = app.handle().clone();
+    let state = handle.state::<BackendState>();
+    // Long enough that the restart never happens before the test shuts down
+    *state.restart_policy.lock_recover() =
+        RestartPolicy { backoff_ms: 60_000, jitter_ms: 0, ..RestartPolicy::default() };
+
+    spawn_backend(&handle).await.unwrap();
+    let seen = events_until(&mut events, "backend-restarting").await;
+    assert_eq!(phases(&seen), ["spawning", "waiting_for_ready", "restarting"]);
+    let (_, restarting) = seen.last().unwrap();
+    assert_eq!(restarting["attempt"], 1);
+    assert_eq!(restarting["exit_code"], MOCK_CRASH_CODE);
+    assert_eq!(restarting["stderr"][1], "RuntimeError: mock backend crashed on startup");
+
+    let last_error = state.last_error.lock_recover().clone().unwrap();
+    assert!(last_error.message.contains("RuntimeError: mock backend crashed on startup"));
+    shut_down(&handle).await;
+    let _ = std::fs::remove_file(std::env::current_exe().unwrap().with_file_name(sidecar));
+}
@@ -0,0 +1,158 @@
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::Connector;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{duration_ms, env_or, url_host, TlsConfig};
+use crate::health::backoff;
+use crate::sync::LockExt;
+use crate::{AppHandle, BackendState, LOG_TARGET};
+
+/// Tunables for the WebSocket bridge carrying live key-stream data
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    // WebSocket endpoint on the backend
+    pub path: String,
+    #[serde(rename = "connect_timeout_ms", with = "duration_ms")]
+    pub connect_timeout: Duration,
+    #[serde(rename = "initial_delay_ms", with = "duration_ms")]
+    pub initial_delay: Duration,
+    #[serde(rename = "max_delay_ms", with = "duration_ms")]
+    pub max_delay: Duration,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            path: "/ws/stream".to_string(),
+            connect_timeout: Duration::from_secs(5),
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn apply_env(&mut self) {
+        if let Ok(path) = std::env::var("QKD_BACKEND_STREAM_PATH") {
+            self.path = path;
+        }
+        self.connect_timeout = Duration::from_millis(env_or(
+            "QKD_BACKEND_STREAM_TIMEOUT_MS",
+            self.connect_timeout.as_millis() as u64,
+        ));
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct StreamStatusPayload {
+    connected: bool,
+    error: Option<String>,
+}
+
+/// Forward messages from the backend's stream socket as `qkd-stream` events until cancelled.
+/// Waits for the backend to be ready and reconnects with backoff whenever the socket drops,
+/// which also covers the backend being restarted mid-stream.
+pub async fn run_bridge(app: AppHandle, config: StreamConfig, cancel: CancellationToken) {
+    let state = app.state::<BackendState>();
+    let mut delay = config.initial_delay;
+    loop {
+        if state.is_ready() {
+            let backend = state.config();
+            let port = *state.port.lock_recover();
+            let ws_scheme = if backend.tls.enabled { "wss" } else { "ws" };
+            let url = format!("{}://{}:{}{}", ws_scheme, url_host(&backend.host), port, config.path);
+            let connector = match tls_connector(&backend.tls) {
+                Ok(connector) => connector,
+                Err(e) => {
+                    // Retrying can't fix a bad certificate, stop so `start_stream` can try again
+                    log::warn!(target: LOG_TARGET, "Key stream disabled: {}", e);
+                    emit_status(&app, false, Some(e));
+                    state.stream.lock_recover().take();
+                    return;
+                }
+            };
+
+            let connect = tokio::time::timeout(
+                config.connect_timeout,
+                tokio_tungstenite::connect_async_tls_with_config(url.as_str(), None, false, connector),
+            );
+            let result = tokio::select! {
+                _ = cancel.cancelled() => return,
+                result = connect => result,
+            };
+            match result {
+                Ok(Ok((mut socket, _))) => {
+                    log::info!(target: LOG_TARGET, "Key stream connected to {}", url);
+                    emit_status(&app, true, None);
+                    delay = config.initial_delay;
+
+                    let error = loop {
+                        let message = tokio::select! {
+                            _ = cancel.cancelled() => {
+                                let _ = socket.close(None).await;
+                                return;
+                            }
+                            message = socket.next() => message,
+                        };
+                        match message {
+                            Some(Ok(Message::Text(text))) => forward(&app, &text),
+                            Some(Ok(Message::Binary(bytes))) => forward(&app, &String::from_utf8_lossy(&bytes)),
+                            Some(Ok(Message::Close(_))) | None => break None,
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => break Some(e.to_string()),
+                        }
+                    };
+                    log::warn!(target: LOG_TARGET, "Key stream disconnected, reconnecting");
+                    emit_status(&app, false, error);
+                }
+                Ok(Err(e)) => {
+                    log::warn!(target: LOG_TARGET, "Key stream connection to {} failed: {}", url, e);
+                    emit_status(&app, false, Some(e.to_string()));
+                }
+                Err(_) => {
+                    log::warn!(target: LOG_TARGET, "Key stream connection to {} timed out", url);
+                    emit_status(&app, false, Some("Connection timed out".to_string()));
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        delay = backoff(delay, config.max_delay);
+    }
+}
+
+/// Pass JSON messages through as-is, anything else as a string
+fn forward(app: &AppHandle, text: &str) {
+    let payload = serde_json::from_str::<serde_json::Value>(text)
+        .unwrap_or_else(|_| serde_json::Value::String(text.to_string()));
+    let _ = app.emit("qkd-stream", payload);
+}
+
+fn emit_status(app: &AppHandle, connected: bool, error: Option<String>) {
+    let _ = app.emit("qkd-stream-status", StreamStatusPayload { connected, error });
+}
+
+/// TLS settings for `wss://`, matching the ones the HTTP client uses. `None` for plain `ws://`.
+fn tls_connector(tls: &TlsConfig) -> Result<Option<Connector>, String> {
+    if !tls.enabled {
+        return Ok(None);
+    }
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = &tls.ca_cert {
+        let pem = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let cert = native_tls::Certificate::from_pem(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+        builder.add_root_certificate(cert);
+    }
+    builder.danger_accept_invalid_certs(tls.accept_invalid_certs);
+    let connector = builder.build().map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    Ok(Some(Connector::NativeTls(connector)))
+}
@@ -0,0 +1,46 @@
+use std::sync::{Mutex, MutexGuard};
+
+use crate::LOG_TARGET;
+
+/// Locking that survives a panic in another holder of the mutex
+pub trait LockExt<T> {
+    /// Lock the mutex, recovering the guard if a previous holder panicked.
+    /// The state is still usable for everything kept here (flags, counters, buffers), so a single
+    /// panicked task shouldn't take backend tracking down with it.
+    fn lock_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            log::warn!(target: LOG_TARGET, "Recovered a poisoned lock, a task panicked while holding it");
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let ready = Arc::new(Mutex::new(false));
+        let holder = Arc::clone(&ready);
+        let panicked = std::thread::spawn(move || {
+            let mut guard = holder.lock().unwrap();
+            *guard = true;
+            panic!("task panicked while holding the lock");
+        })
+        .join();
+        assert!(panicked.is_err() && ready.is_poisoned());
+
+        // The value written before the panic is still there, and the lock is usable again
+        assert!(*ready.lock_recover());
+        assert!(!ready.is_poisoned());
+        *ready.lock_recover() = false;
+        assert!(!*ready.lock().unwrap());
+    }
+}